@@ -0,0 +1,200 @@
+use crate::{
+    cache::{Cache, Key},
+    common::{Playfield, Standard},
+    interpret::{self, Io, Limits, OsRandom, RandomSource, StdIo},
+    ir::Program,
+    optimize::{self, Options, Stats, StepRun},
+    parse,
+};
+
+/// A builder for compiling Befunge programs.
+pub struct Builder {
+    /// The playfield.
+    playfield: Playfield,
+
+    /// The options for optimizing the program.
+    options: Options,
+
+    /// The limits for interpreting the program.
+    limits: Limits,
+
+    /// The cache to load the compiled program from and store it to.
+    cache: Option<Cache>,
+}
+
+impl Builder {
+    /// Creates a new builder from source code with the default standard.
+    pub fn new(source: &str) -> Self {
+        Self::from_playfield(Playfield::new(source, Standard::default()))
+    }
+
+    /// Creates a new builder from a playfield.
+    pub fn from_playfield(playfield: Playfield) -> Self {
+        Self {
+            playfield,
+            options: Options::default(),
+            limits: Limits::default(),
+            cache: None,
+        }
+    }
+
+    /// Sets the options for optimizing the program.
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets the limits for interpreting the program.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sets the cache to load the compiled program from and store it to.
+    pub fn cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Parses and optimizes the program. If a cache is set, then the compiled
+    /// program is loaded from the cache if it is there, or stored to the cache
+    /// after compiling it. Cache errors are ignored, since the program can
+    /// always be compiled again.
+    pub fn compile(mut self) -> Compiled {
+        let Some(cache) = self.cache.take() else {
+            return self.compile_observed(|_, _| {});
+        };
+
+        let key = Key::new(&self.playfield, &self.options);
+        if let Ok(Some((program, stats))) = cache.load(&key) {
+            return Compiled {
+                program,
+                playfield: self.playfield,
+                stats,
+                limits: self.limits,
+                options: self.options,
+            };
+        }
+
+        let compiled = self.compile_observed(|_, _| {});
+        let _ = cache.store(&key, &compiled.program, &compiled.stats);
+        compiled
+    }
+
+    /// Parses and optimizes the program. An observer is called with the program
+    /// after each compilation stage. The cache is not used, since the program
+    /// must be compiled to observe its stages.
+    pub fn compile_observed(self, mut observer: impl FnMut(Stage, &Program)) -> Compiled {
+        let playfield = self.playfield;
+        let (mut program, flow_graph) = parse::parse_program(&playfield);
+        observer(Stage::Parse, &program);
+        let stats = optimize::optimize_program_observed(
+            &mut program,
+            &flow_graph,
+            &playfield,
+            &self.options,
+            |r, p| observer(Stage::Step(*r), p),
+        );
+
+        Compiled {
+            program,
+            playfield,
+            stats,
+            limits: self.limits,
+            options: self.options,
+        }
+    }
+}
+
+/// A stage of compiling a program.
+#[derive(Clone, Copy)]
+pub enum Stage {
+    /// A stage where the program was parsed.
+    Parse,
+
+    /// A stage where an optimization step was run.
+    Step(StepRun),
+}
+
+/// A compiled Befunge program with its playfield.
+pub struct Compiled {
+    /// The program.
+    program: Program,
+
+    /// The playfield.
+    playfield: Playfield,
+
+    /// The statistics about optimizing the program.
+    stats: Stats,
+
+    /// The limits for interpreting the program.
+    limits: Limits,
+
+    /// The options for optimizing the program if it is recompiled.
+    options: Options,
+}
+
+impl Compiled {
+    /// Returns the program.
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Returns the playfield.
+    pub fn playfield(&self) -> &Playfield {
+        &self.playfield
+    }
+
+    /// Returns the statistics about optimizing the program.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Converts the compiled program to its program and playfield.
+    pub fn into_parts(self) -> (Program, Playfield) {
+        (self.program, self.playfield)
+    }
+
+    /// Interprets the program with the standard input and output streams and an
+    /// operating system random source and returns the final playfield.
+    pub fn run(self) -> interpret::Result<Playfield> {
+        self.run_with(&mut StdIo::default(), &mut OsRandom)
+    }
+
+    /// Interprets the program with an I/O backend and a random source and
+    /// returns the final playfield.
+    pub fn run_with(
+        mut self,
+        io: &mut impl Io,
+        random: &mut impl RandomSource,
+    ) -> interpret::Result<Playfield> {
+        interpret::interpret_program(
+            &self.program,
+            &mut self.playfield,
+            io,
+            random,
+            self.limits,
+            &self.options,
+        )?;
+        Ok(self.playfield)
+    }
+
+    /// Compiles the program to machine code and runs it with an I/O backend and
+    /// a random source and returns the final playfield.
+    #[cfg(feature = "jit")]
+    pub fn run_jit_with(
+        mut self,
+        io: &mut impl Io,
+        random: &mut impl RandomSource,
+    ) -> interpret::Result<Playfield> {
+        interpret::jit_program(
+            &self.program,
+            &mut self.playfield,
+            io,
+            random,
+            self.limits,
+            &self.options,
+        )?;
+        Ok(self.playfield)
+    }
+}
@@ -0,0 +1,59 @@
+use std::{
+    error,
+    fmt::{self, Display, Formatter},
+    io, result,
+};
+
+/// A result that may contain a cache error.
+pub type Result<T> = result::Result<T, Error>;
+
+/// An error raised while loading or storing a cached program.
+#[derive(Debug)]
+pub enum Error {
+    /// An error caused by a file that is not a cached program.
+    InvalidHeader,
+
+    /// An error caused by a cached program from an unsupported format or
+    /// Fungus version.
+    UnsupportedVersion,
+
+    /// An error caused by a cached program for a different key.
+    KeyMismatch,
+
+    /// An error caused by a cached program that could not be decoded.
+    Corrupt,
+
+    /// An error caused by an I/O error while reading or writing a cache file.
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::InvalidHeader | Self::UnsupportedVersion | Self::KeyMismatch | Self::Corrupt => {
+                None
+            }
+            Self::Io(e) => Some(e),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeader => f.write_str("file is not a cached program"),
+            Self::UnsupportedVersion => {
+                f.write_str("cached program is from an unsupported version")
+            }
+            Self::KeyMismatch => f.write_str("cached program is for a different source"),
+            Self::Corrupt => f.write_str("cached program is corrupt"),
+            Self::Io(e) => write!(f, "could not access cache file: {e}"),
+        }
+    }
+}
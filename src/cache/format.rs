@@ -0,0 +1,494 @@
+//! The binary format of cached programs.
+//!
+//! A cached program starts with a header:
+//! * The magic bytes `FUNGUSPG`.
+//! * The format version as a `u32`.
+//! * The Fungus version as a string.
+//! * The key as a `u32` byte length followed by its bytes.
+//!
+//! The header is followed by the optimization statistics and the program's
+//! blocks in label order. Integers are little-endian, `usize` values are stored
+//! as `u64`, and strings are stored as a `u32` byte length followed by UTF-8
+//! bytes. Enums are stored as a `u8` tag followed by their fields.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    ir::{
+        Block, Exit, Instruction, Label, Program, State,
+        ops::{BinOp, DivOp, UnOp},
+        state::{Direction, Mode},
+    },
+    optimize::{Stats, Step},
+};
+
+use super::{Error, Key, Result};
+
+/// The magic bytes at the start of a cached program.
+const MAGIC: [u8; 8] = *b"FUNGUSPG";
+
+/// The version of the format. This must be changed whenever the format changes.
+const FORMAT_VERSION: u32 = 3;
+
+/// The version of Fungus. Cached programs from other versions are rejected
+/// because they may have been optimized differently.
+const FUNGUS_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Encodes a compiled program and its optimization statistics for a key.
+pub fn encode(key: &Key, program: &Program, stats: &Stats) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    encoder.bytes.extend_from_slice(&MAGIC);
+    encoder.u32(FORMAT_VERSION);
+    encoder.str(FUNGUS_VERSION);
+    encoder.len(key.bytes.len());
+    encoder.bytes.extend_from_slice(&key.bytes);
+    encoder.stats(stats);
+    encoder.len(program.blocks.len());
+    for (label, block) in &program.blocks {
+        encoder.label(label);
+        encoder.block(block);
+    }
+
+    encoder.bytes
+}
+
+/// Decodes a compiled program and its optimization statistics for a key.
+/// Positions and states outside of the key's playfield bounds are rejected.
+pub fn decode(bytes: &[u8], key: &Key) -> Result<(Program, Stats)> {
+    let mut decoder = Decoder {
+        bytes,
+        bounds: key.bounds,
+    };
+
+    if decoder.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+        return Err(Error::InvalidHeader);
+    }
+
+    if decoder.u32()? != FORMAT_VERSION || decoder.str()? != FUNGUS_VERSION {
+        return Err(Error::UnsupportedVersion);
+    }
+
+    let key_len = decoder.len()?;
+    if decoder.take(key_len)? != key.bytes {
+        return Err(Error::KeyMismatch);
+    }
+
+    let stats = decoder.stats()?;
+    let mut blocks = BTreeMap::new();
+    for _ in 0..decoder.len()? {
+        let label = decoder.label()?;
+        let block = decoder.block()?;
+        blocks.insert(label, block);
+    }
+
+    if !decoder.bytes.is_empty() {
+        return Err(Error::Corrupt);
+    }
+
+    // Blocks are assumed to exist elsewhere, so a program with missing blocks
+    // is rejected here instead of causing a panic later.
+    let is_complete = blocks.contains_key(&Label::Main)
+        && blocks
+            .values()
+            .flat_map(|b| b.exit.to_labels())
+            .all(|l| blocks.contains_key(l));
+
+    if !is_complete {
+        return Err(Error::Corrupt);
+    }
+
+    Ok((Program { blocks }, stats))
+}
+
+/// An encoder for the binary format.
+#[derive(Default)]
+struct Encoder {
+    /// The encoded bytes.
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    /// Encodes a `u8`.
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    /// Encodes a `u32`.
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Encodes a `u64`.
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// Encodes a `usize`.
+    fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    /// Encodes a length.
+    fn len(&mut self, len: usize) {
+        self.u32(u32::try_from(len).expect("length should fit in 32 bits"));
+    }
+
+    /// Encodes a string.
+    fn str(&mut self, value: &str) {
+        self.len(value.len());
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    /// Encodes optimization statistics.
+    fn stats(&mut self, stats: &Stats) {
+        self.usize(stats.blocks_before);
+        self.usize(stats.instructions_before);
+        self.usize(stats.blocks_after);
+        self.usize(stats.instructions_after);
+        self.usize(stats.passes);
+        self.len(stats.step_changes.len());
+        for (step, changes) in &stats.step_changes {
+            self.u8(u8::try_from(step.index()).expect("step index should fit in a byte"));
+            self.usize(*changes);
+        }
+
+        self.usize(stats.dynamic_puts);
+        self.usize(stats.lowered_puts);
+        self.usize(stats.data_puts);
+        self.usize(stats.guarded_puts);
+    }
+
+    /// Encodes a label.
+    fn label(&mut self, label: &Label) {
+        match label {
+            Label::Main => self.u8(0),
+            Label::State(s) => {
+                self.u8(1);
+                self.state(s);
+            }
+        }
+    }
+
+    /// Encodes a state.
+    fn state(&mut self, state: &State) {
+        self.usize(state.x);
+        self.usize(state.y);
+        self.mode(state.mode);
+        self.u8(match state.direction {
+            Direction::Right => 0,
+            Direction::Down => 1,
+            Direction::Left => 2,
+            Direction::Up => 3,
+        });
+    }
+
+    /// Encodes a mode.
+    fn mode(&mut self, mode: Mode) {
+        self.u8(match mode {
+            Mode::Command => 0,
+            Mode::String => 1,
+        });
+    }
+
+    /// Encodes a block.
+    fn block(&mut self, block: &Block) {
+        self.len(block.instructions.len());
+        for instruction in &block.instructions {
+            self.instruction(instruction);
+        }
+
+        match &block.exit {
+            Exit::Jump(l) => {
+                self.u8(0);
+                self.label(l);
+            }
+            Exit::Random(r, d, l, u) => {
+                self.u8(1);
+                for label in [r, d, l, u] {
+                    self.label(label);
+                }
+            }
+            Exit::Branch(t, e) => {
+                self.u8(2);
+                self.label(t);
+                self.label(e);
+            }
+            Exit::End => self.u8(3),
+        }
+    }
+
+    /// Encodes an instruction.
+    fn instruction(&mut self, instruction: &Instruction) {
+        match instruction {
+            Instruction::Push(v) => {
+                self.u8(0);
+                self.u32(v.into_i32().cast_unsigned());
+            }
+            Instruction::Unary(UnOp::Not) => {
+                self.u8(1);
+                self.u8(0);
+            }
+            Instruction::Binary(o) => {
+                self.u8(2);
+                self.u8(match o {
+                    BinOp::Add => 0,
+                    BinOp::Subtract => 1,
+                    BinOp::Multiply => 2,
+                    BinOp::Greater => 3,
+                    BinOp::Divide => 4,
+                    BinOp::Modulo => 5,
+                });
+            }
+            Instruction::Divide(o) => {
+                self.u8(3);
+                self.u8(match o {
+                    DivOp::Quotient => 0,
+                    DivOp::Remainder => 1,
+                });
+            }
+            Instruction::Duplicate => self.u8(4),
+            Instruction::Swap => self.u8(5),
+            Instruction::Pop => self.u8(6),
+            Instruction::OutputInt => self.u8(7),
+            Instruction::OutputChar => self.u8(8),
+            Instruction::Get => self.u8(9),
+            Instruction::Put(s) => {
+                self.u8(10);
+                self.state(s);
+            }
+            Instruction::InputInt => self.u8(11),
+            Instruction::InputChar => self.u8(12),
+            Instruction::Print(s) => {
+                self.u8(13);
+                self.str(s);
+            }
+            Instruction::GetAt(x, y) => {
+                self.u8(14);
+                self.usize(*x);
+                self.usize(*y);
+            }
+            Instruction::PutAt(x, y) => {
+                self.u8(15);
+                self.usize(*x);
+                self.usize(*y);
+            }
+            Instruction::PutData => self.u8(16),
+            Instruction::PutAtGuarded(x, y, m, s) => {
+                self.u8(17);
+                self.usize(*x);
+                self.usize(*y);
+                self.mode(*m);
+                self.state(s);
+            }
+        }
+    }
+}
+
+/// A decoder for the binary format.
+struct Decoder<'a> {
+    /// The bytes that have not been decoded yet.
+    bytes: &'a [u8],
+
+    /// The playfield bounds in cells.
+    bounds: (usize, usize),
+}
+
+impl<'a> Decoder<'a> {
+    /// Decodes a number of bytes.
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let (taken, rest) = self.bytes.split_at_checked(len).ok_or(Error::Corrupt)?;
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    /// Decodes a fixed number of bytes.
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self
+            .take(N)?
+            .try_into()
+            .expect("slice should have length N"))
+    }
+
+    /// Decodes a `u8`.
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    /// Decodes a `u32`.
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    /// Decodes a `u64`.
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    /// Decodes a `usize`.
+    fn usize(&mut self) -> Result<usize> {
+        usize::try_from(self.u64()?).map_err(|_| Error::Corrupt)
+    }
+
+    /// Decodes a length.
+    fn len(&mut self) -> Result<usize> {
+        usize::try_from(self.u32()?).map_err(|_| Error::Corrupt)
+    }
+
+    /// Decodes a string.
+    fn str(&mut self) -> Result<&'a str> {
+        let len = self.len()?;
+        str::from_utf8(self.take(len)?).map_err(|_| Error::Corrupt)
+    }
+
+    /// Decodes optimization statistics.
+    fn stats(&mut self) -> Result<Stats> {
+        let blocks_before = self.usize()?;
+        let instructions_before = self.usize()?;
+        let blocks_after = self.usize()?;
+        let instructions_after = self.usize()?;
+        let passes = self.usize()?;
+        let mut step_changes = Vec::new();
+        for _ in 0..self.len()? {
+            let step = *Step::ALL
+                .get(usize::from(self.u8()?))
+                .ok_or(Error::Corrupt)?;
+
+            step_changes.push((step, self.usize()?));
+        }
+
+        Ok(Stats {
+            blocks_before,
+            instructions_before,
+            blocks_after,
+            instructions_after,
+            passes,
+            step_changes,
+            dynamic_puts: self.usize()?,
+            lowered_puts: self.usize()?,
+            data_puts: self.usize()?,
+            guarded_puts: self.usize()?,
+        })
+    }
+
+    /// Decodes a label.
+    fn label(&mut self) -> Result<Label> {
+        match self.u8()? {
+            0 => Ok(Label::Main),
+            1 => Ok(Label::State(self.state()?)),
+            _ => Err(Error::Corrupt),
+        }
+    }
+
+    /// Decodes a position in the playfield bounds.
+    fn position(&mut self) -> Result<(usize, usize)> {
+        let (x, y) = (self.usize()?, self.usize()?);
+        let (width, height) = self.bounds;
+        if x < width && y < height {
+            Ok((x, y))
+        } else {
+            Err(Error::Corrupt)
+        }
+    }
+
+    /// Decodes a state.
+    fn state(&mut self) -> Result<State> {
+        let (x, y) = self.position()?;
+        let mode = self.mode()?;
+        let direction = match self.u8()? {
+            0 => Direction::Right,
+            1 => Direction::Down,
+            2 => Direction::Left,
+            3 => Direction::Up,
+            _ => return Err(Error::Corrupt),
+        };
+
+        Ok(State {
+            y,
+            x,
+            mode,
+            direction,
+        })
+    }
+
+    /// Decodes a mode.
+    fn mode(&mut self) -> Result<Mode> {
+        match self.u8()? {
+            0 => Ok(Mode::Command),
+            1 => Ok(Mode::String),
+            _ => Err(Error::Corrupt),
+        }
+    }
+
+    /// Decodes a block.
+    fn block(&mut self) -> Result<Block> {
+        let len = self.len()?;
+
+        // The capacity is limited so that a corrupt length can not cause a
+        // huge allocation.
+        let mut instructions = Vec::with_capacity(len.min(self.bytes.len()));
+        for _ in 0..len {
+            instructions.push(self.instruction()?);
+        }
+
+        let exit = match self.u8()? {
+            0 => Exit::Jump(self.label()?),
+            1 => Exit::Random(self.label()?, self.label()?, self.label()?, self.label()?),
+            2 => Exit::Branch(self.label()?, self.label()?),
+            3 => Exit::End,
+            _ => return Err(Error::Corrupt),
+        };
+
+        Ok(Block { instructions, exit })
+    }
+
+    /// Decodes an instruction.
+    fn instruction(&mut self) -> Result<Instruction> {
+        let instruction = match self.u8()? {
+            0 => Instruction::Push(self.u32()?.cast_signed().into()),
+            1 => match self.u8()? {
+                0 => Instruction::Unary(UnOp::Not),
+                _ => return Err(Error::Corrupt),
+            },
+            2 => Instruction::Binary(match self.u8()? {
+                0 => BinOp::Add,
+                1 => BinOp::Subtract,
+                2 => BinOp::Multiply,
+                3 => BinOp::Greater,
+                4 => BinOp::Divide,
+                5 => BinOp::Modulo,
+                _ => return Err(Error::Corrupt),
+            }),
+            3 => Instruction::Divide(match self.u8()? {
+                0 => DivOp::Quotient,
+                1 => DivOp::Remainder,
+                _ => return Err(Error::Corrupt),
+            }),
+            4 => Instruction::Duplicate,
+            5 => Instruction::Swap,
+            6 => Instruction::Pop,
+            7 => Instruction::OutputInt,
+            8 => Instruction::OutputChar,
+            9 => Instruction::Get,
+            10 => Instruction::Put(self.state()?),
+            11 => Instruction::InputInt,
+            12 => Instruction::InputChar,
+            13 => Instruction::Print(self.str()?.into()),
+            14 => {
+                let (x, y) = self.position()?;
+                Instruction::GetAt(x, y)
+            }
+            15 => {
+                let (x, y) = self.position()?;
+                Instruction::PutAt(x, y)
+            }
+            16 => Instruction::PutData,
+            17 => {
+                let (x, y) = self.position()?;
+                Instruction::PutAtGuarded(x, y, self.mode()?, self.state()?)
+            }
+            _ => return Err(Error::Corrupt),
+        };
+
+        Ok(instruction)
+    }
+}
@@ -0,0 +1,157 @@
+mod error;
+mod format;
+
+pub use error::{Error, Result};
+
+use std::{
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::PathBuf,
+};
+
+use crate::{
+    common::{Playfield, Standard},
+    ir::Program,
+    optimize::{Level, Options, Stats},
+};
+
+/// A cache of compiled programs stored as files in a directory.
+pub struct Cache {
+    /// The directory.
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// Creates a new cache in a directory. The directory is created when the
+    /// first program is stored.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Loads a compiled program and its optimization statistics for a key.
+    /// Returns `Ok(None)` if no program is cached for the key.
+    pub fn load(&self, key: &Key) -> Result<Option<(Program, Stats)>> {
+        match fs::read(self.path(key)) {
+            Ok(bytes) => format::decode(&bytes, key).map(Some),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Stores a compiled program and its optimization statistics for a key.
+    pub fn store(&self, key: &Key, program: &Program, stats: &Stats) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        // The file is written under a temporary name and renamed so that other
+        // processes never load a partially written program.
+        let path = self.path(key);
+        let temp_path = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&temp_path, format::encode(key, program, stats))?;
+        fs::rename(&temp_path, &path).inspect_err(|_| {
+            let _ = fs::remove_file(&temp_path);
+        })?;
+
+        Ok(())
+    }
+
+    /// Returns the path of the file for a key.
+    fn path(&self, key: &Key) -> PathBuf {
+        self.dir.join(format!("{key}.fungus"))
+    }
+}
+
+/// A key for a compiled program, which identifies its source playfield, its
+/// standard, and its optimization options. Cache files are named by a hash of the key, and the
+/// whole key is stored in each file so that hash collisions are rejected.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Key {
+    /// The playfield bounds in cells.
+    bounds: (usize, usize),
+
+    /// The encoded playfield bounds, playfield cells, standard, and
+    /// optimization options.
+    bytes: Vec<u8>,
+}
+
+impl Key {
+    /// Creates a new key from a playfield and optimization options.
+    pub fn new(playfield: &Playfield, options: &Options) -> Self {
+        let mut bytes = Vec::new();
+        let (width, height) = playfield.bounds();
+        bytes.extend_from_slice(&(width as u64).to_le_bytes());
+        bytes.extend_from_slice(&(height as u64).to_le_bytes());
+        for y in 0..height {
+            for x in 0..width {
+                let value = playfield.get(x, y).unwrap_or_default();
+                bytes.extend_from_slice(&value.into_i32().to_le_bytes());
+            }
+        }
+
+        // Playfields that follow different standards may have identical cells
+        // but handle get and put coordinates differently.
+        let standard = match playfield.standard() {
+            Standard::Fungus => 0,
+            Standard::Befunge93Strict => 1,
+        };
+
+        bytes.push(standard);
+        let level = match options.level {
+            Level::None => 0,
+            Level::Once => 1,
+            Level::Full => 2,
+        };
+
+        bytes.push(level);
+
+        // The same steps may be disabled in any order.
+        let mut steps: Vec<usize> = options.disabled_steps.iter().map(|s| s.index()).collect();
+        steps.sort_unstable();
+        steps.dedup();
+        for step in steps {
+            bytes.extend_from_slice(&(step as u64).to_le_bytes());
+        }
+
+        Self {
+            bounds: (width, height),
+            bytes,
+        }
+    }
+
+    /// Returns the hash of the key.
+    fn hash(&self) -> u64 {
+        let mut hasher = Hasher::default();
+        hasher.write(&self.bytes);
+        hasher.state
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.hash())
+    }
+}
+
+/// A 64-bit FNV-1a hasher. A fixed hash function is used so that file names are
+/// stable across Rust versions and runs.
+struct Hasher {
+    /// The hash state.
+    state: u64,
+}
+
+impl Default for Hasher {
+    fn default() -> Self {
+        Self {
+            state: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+}
+
+impl Hasher {
+    /// Adds bytes to the hash.
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state ^= u64::from(*byte);
+            self.state = self.state.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
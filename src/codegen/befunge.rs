@@ -0,0 +1,436 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display},
+};
+
+use crate::{
+    common::{Playfield, Value},
+    ir::{
+        Exit, Instruction, Label, Program,
+        ops::{BinOp, DivOp, UnOp},
+    },
+};
+
+use super::{Error, Result};
+
+/// The maximum width of a Befunge-93 playfield in cells.
+const MAX_WIDTH: usize = 80;
+
+/// The maximum height of a Befunge-93 playfield in cells.
+const MAX_HEIGHT: usize = 25;
+
+/// Generates a Befunge-93 playfield from a program and its playfield.
+///
+/// Each block is laid out as a row of commands running right, followed by a
+/// row for each target of its exit that runs left back to the target's lane.
+/// The lanes are columns on the left edge of the playfield, one for each block
+/// that is jumped to, which run up or down to the block's row. Playfield cells
+/// used by get and put instructions with known positions are moved to a data
+/// row at the top of the playfield.
+///
+/// Returns an error if the program has dynamic get or put instructions, or
+/// guarded puts that may modify the program, which depend on the layout of the
+/// original playfield, or if the generated playfield is larger than a
+/// Befunge-93 playfield.
+pub fn generate_program(program: &Program, playfield: &Playfield) -> Result<String> {
+    let is_dynamic = program
+        .blocks
+        .values()
+        .flat_map(|b| &b.instructions)
+        .any(|i| {
+            matches!(
+                i,
+                Instruction::Get
+                    | Instruction::Put(_)
+                    | Instruction::PutData
+                    | Instruction::PutAtGuarded(_, _, _, _)
+            )
+        });
+
+    if is_dynamic {
+        return Err(Error::DynamicPlayfield);
+    }
+
+    let grid = generate_grid(program, playfield);
+    let (width, height) = grid.bounds();
+    if width > MAX_WIDTH || height > MAX_HEIGHT {
+        return Err(Error::PlayfieldTooLarge(width, height));
+    }
+
+    Ok(grid.to_string())
+}
+
+/// Generates a grid of commands from a program and its playfield.
+fn generate_grid(program: &Program, playfield: &Playfield) -> Grid {
+    let instructions = program.blocks.values().flat_map(|b| &b.instructions);
+    let mut data = BTreeMap::new();
+    for instruction in instructions {
+        if let Instruction::GetAt(x, y) | Instruction::PutAt(x, y) = instruction {
+            let next = data.len() + 1;
+            data.entry((*x, *y)).or_insert(next);
+        }
+    }
+
+    // The data row is above the main block, so the program starts by moving
+    // down the main block's lane.
+    let has_data_row = !data.is_empty();
+    let jump_targets: BTreeSet<&Label> = program
+        .blocks
+        .values()
+        .flat_map(|b| targets(&b.exit))
+        .collect();
+    let lanes: BTreeMap<&Label, usize> = program
+        .blocks
+        .keys()
+        .filter(|l| jump_targets.contains(l) || (has_data_row && **l == Label::Main))
+        .zip(0..)
+        .collect();
+
+    let mut rows = BTreeMap::new();
+    let mut row = usize::from(has_data_row);
+    for (label, block) in &program.blocks {
+        rows.insert(label, row);
+        row += 1 + targets(&block.exit).len();
+    }
+
+    // Cells with values that can't be stored as characters are put in the data
+    // row when the program starts.
+    let mut grid = Grid::default();
+    let mut put_cells = Vec::new();
+    if has_data_row {
+        grid.set(0, 0, 'v');
+        for (&(x, y), &column) in &data {
+            let value = playfield.get(x, y).unwrap_or_default();
+            match cell_char(value) {
+                Some(c) => grid.set(column, 0, c),
+                None => put_cells.push((column, value.into_i32())),
+            }
+        }
+    }
+
+    let start = lanes.len();
+    let mut encoder = Encoder::new(&data);
+    for (label, block) in &program.blocks {
+        let row = rows[label];
+        if let Some(&lane) = lanes.get(label) {
+            grid.set(lane, row, '>');
+        }
+
+        if *label == Label::Main {
+            for &(column, value) in &put_cells {
+                encoder.put_data_cell(column, value);
+            }
+        }
+
+        for instruction in &block.instructions {
+            encoder.encode_instruction(instruction);
+        }
+
+        let mut code = encoder.finish();
+
+        // The random exit skips the cell before its end on the row below.
+        if code.is_empty() && matches!(block.exit, Exit::Random(..)) {
+            code.push(' ');
+        }
+
+        grid.set_str(start, row, &code);
+        let end = start + code.chars().count();
+        let returns = match &block.exit {
+            Exit::Jump(l) => {
+                grid.set(end, row, 'v');
+                grid.set(end, row + 1, '<');
+                vec![(row + 1, l)]
+            }
+            Exit::Random(r, d, l, u) => {
+                grid.set(end, row, 'v');
+                grid.set(end, row + 1, '#');
+                grid.set(end + 1, row + 1, '<');
+                grid.set(end, row + 2, '<');
+                grid.set(end, row + 3, '?');
+                grid.set(end + 1, row + 3, '^');
+                grid.set(end, row + 4, '<');
+                vec![(row + 1, r), (row + 4, d), (row + 3, l), (row + 2, u)]
+            }
+            Exit::Branch(t, e) => {
+                grid.set(end, row, 'v');
+                grid.set(end, row + 1, '_');
+                grid.set(end + 1, row + 1, 'v');
+                grid.set(end + 1, row + 2, '<');
+                vec![(row + 1, t), (row + 2, e)]
+            }
+            Exit::End => {
+                grid.set(end, row, '@');
+                Vec::new()
+            }
+        };
+
+        for (row, target) in returns {
+            let turn = if rows[target] < row { '^' } else { 'v' };
+            grid.set(lanes[target], row, turn);
+        }
+    }
+
+    grid
+}
+
+/// Returns the targets of an exit, with a row for each target.
+fn targets(exit: &Exit) -> Vec<&Label> {
+    match exit {
+        Exit::Jump(l) => vec![l],
+        Exit::Random(r, d, l, u) => vec![r, d, l, u],
+        Exit::Branch(t, e) => vec![t, e],
+        Exit::End => Vec::new(),
+    }
+}
+
+/// An encoder from instructions to Befunge commands.
+struct Encoder<'a> {
+    /// The encoded commands.
+    code: String,
+
+    /// The characters of a string literal that have not been encoded yet.
+    literal: String,
+
+    /// The columns in the data row of playfield cells.
+    data: &'a BTreeMap<(usize, usize), usize>,
+
+    /// The shortest known encodings of numbers.
+    numbers: BTreeMap<u32, String>,
+}
+
+impl<'a> Encoder<'a> {
+    /// Creates a new encoder with the columns of playfield cells in the data
+    /// row.
+    fn new(data: &'a BTreeMap<(usize, usize), usize>) -> Self {
+        Self {
+            code: String::new(),
+            literal: String::new(),
+            data,
+            numbers: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the encoded commands and clears them.
+    fn finish(&mut self) -> String {
+        self.flush_literal();
+        std::mem::take(&mut self.code)
+    }
+
+    /// Encodes an instruction.
+    fn encode_instruction(&mut self, instruction: &Instruction) {
+        let command = match instruction {
+            Instruction::Push(v) => return self.push(v.into_i32()),
+            Instruction::Unary(UnOp::Not) => '!',
+            Instruction::Binary(BinOp::Add) => '+',
+            Instruction::Binary(BinOp::Subtract) => '-',
+            Instruction::Binary(BinOp::Multiply) => '*',
+            Instruction::Binary(BinOp::Greater) => '`',
+            Instruction::Binary(BinOp::Divide) | Instruction::Divide(DivOp::Quotient) => '/',
+            Instruction::Binary(BinOp::Modulo) | Instruction::Divide(DivOp::Remainder) => '%',
+            Instruction::Duplicate => ':',
+            Instruction::Swap => '\\',
+            Instruction::Pop => '$',
+            Instruction::OutputInt => '.',
+            Instruction::OutputChar => ',',
+            Instruction::Get
+            | Instruction::Put(_)
+            | Instruction::PutData
+            | Instruction::PutAtGuarded(_, _, _, _) => {
+                unreachable!("dynamic get and put instructions should not be encoded")
+            }
+            Instruction::InputInt => '&',
+            Instruction::InputChar => '~',
+            Instruction::Print(s) => {
+                for c in s.chars().rev() {
+                    self.push(u32::from(c).cast_signed());
+                }
+
+                self.flush_literal();
+                self.code.extend(s.chars().map(|_| ','));
+                return;
+            }
+            Instruction::GetAt(x, y) => {
+                self.push_data_position(*x, *y);
+                'g'
+            }
+            Instruction::PutAt(x, y) => {
+                self.push_data_position(*x, *y);
+                'p'
+            }
+        };
+
+        self.flush_literal();
+        self.code.push(command);
+    }
+
+    /// Encodes putting a value in a column of the data row.
+    fn put_data_cell(&mut self, column: usize, value: i32) {
+        self.push(value);
+        self.push(i32::try_from(column).expect("data column should fit in 32 bits"));
+        self.push(0);
+        self.flush_literal();
+        self.code.push('p');
+    }
+
+    /// Encodes pushing the position of a playfield cell in the data row.
+    fn push_data_position(&mut self, x: usize, y: usize) {
+        let column = i32::try_from(self.data[&(x, y)]).expect("data column should fit in 32 bits");
+        self.push(column);
+        self.push(0);
+    }
+
+    /// Encodes pushing a value. Printable characters are added to a string
+    /// literal.
+    fn push(&mut self, value: i32) {
+        match literal_char(value) {
+            Some(c) => self.literal.push(c),
+            None => {
+                self.flush_literal();
+                let number = self.number(value);
+                self.code.push_str(&number);
+            }
+        }
+    }
+
+    /// Encodes the string literal if it is not empty.
+    fn flush_literal(&mut self) {
+        let mut chars = self.literal.chars();
+        match (chars.next(), chars.next()) {
+            (None, _) => {}
+            (Some(c), None) => {
+                // A single character may be shorter as an expression.
+                let number = self.unsigned_number(c.into());
+                self.code.push_str(&number);
+            }
+            _ => {
+                self.code.push('"');
+                self.code.push_str(&self.literal);
+                self.code.push('"');
+            }
+        }
+
+        self.literal.clear();
+    }
+
+    /// Returns the shortest known commands to push a value.
+    fn number(&mut self, value: i32) -> String {
+        if value < 0 {
+            // The absolute value of `i32::MIN` wraps back to itself.
+            format!("0{}-", self.unsigned_number(value.unsigned_abs()))
+        } else {
+            self.unsigned_number(value.cast_unsigned())
+        }
+    }
+
+    /// Returns the shortest known commands to push a value that wraps to a
+    /// 32-bit integer.
+    fn unsigned_number(&mut self, value: u32) -> String {
+        if value <= 9 {
+            return value.to_string();
+        }
+
+        if let Some(number) = self.numbers.get(&value) {
+            return number.clone();
+        }
+
+        let (quotient, remainder) = (value / 9, value % 9);
+        let mut best = self.unsigned_number(quotient) + "9*";
+        if remainder > 0 {
+            best += &format!("{remainder}+");
+        }
+
+        let mut candidates = Vec::new();
+        if let Some(c) = literal_char(value.cast_signed()) {
+            candidates.push(format!("\"{c}\""));
+        }
+
+        if value <= 18 {
+            candidates.push(format!("9{}+", value - 9));
+        }
+
+        for factor in 2..=9 {
+            if value.is_multiple_of(factor) && value / factor > 1 {
+                candidates.push(format!("{}{factor}*", self.unsigned_number(value / factor)));
+            }
+        }
+
+        for candidate in candidates {
+            if candidate.len() < best.len() {
+                best = candidate;
+            }
+        }
+
+        self.numbers.insert(value, best.clone());
+        best
+    }
+}
+
+/// Returns the character for a value if it can be pushed in a string literal.
+fn literal_char(value: i32) -> Option<char> {
+    u8::try_from(value)
+        .ok()
+        .filter(|b| matches!(b, b' '..=b'~') && *b != b'"')
+        .map(char::from)
+}
+
+/// Returns the character for a value if it can be stored in a playfield cell of
+/// generated source code.
+fn cell_char(value: Value) -> Option<char> {
+    u32::try_from(value.into_i32())
+        .ok()
+        .and_then(char::from_u32)
+        .filter(|c| !c.is_control())
+}
+
+/// A grid of cells that grows when cells are set.
+#[derive(Default)]
+struct Grid {
+    /// The rows of cells.
+    rows: Vec<Vec<char>>,
+}
+
+impl Grid {
+    /// Returns the bounds in cells, ignoring trailing spaces.
+    fn bounds(&self) -> (usize, usize) {
+        let width = self
+            .rows
+            .iter()
+            .map(|r| r.iter().rposition(|c| *c != ' ').map_or(0, |i| i + 1))
+            .max()
+            .unwrap_or_default();
+
+        (width, self.rows.len())
+    }
+
+    /// Sets the cell at a position.
+    fn set(&mut self, x: usize, y: usize, c: char) {
+        if self.rows.len() <= y {
+            self.rows.resize_with(y + 1, Vec::new);
+        }
+
+        let row = &mut self.rows[y];
+        if row.len() <= x {
+            row.resize(x + 1, ' ');
+        }
+
+        row[x] = c;
+    }
+
+    /// Sets the cells in a row starting at a position.
+    fn set_str(&mut self, x: usize, y: usize, s: &str) {
+        for (i, c) in s.chars().enumerate() {
+            self.set(x + i, y, c);
+        }
+    }
+}
+
+impl Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.rows {
+            let line: String = row.iter().collect();
+            writeln!(f, "{}", line.trim_end_matches(' '))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,560 @@
+use std::fmt::{self, Write};
+
+use crate::{
+    common::Playfield,
+    ir::{
+        Block, Exit, Instruction, Label, Program, State,
+        ops::{BinOp, DivOp, UnOp},
+        state::{Direction, Mode},
+    },
+};
+
+/// Generates a standalone C source file from a program and its playfield.
+pub fn generate_program(program: &Program, playfield: &Playfield) -> String {
+    let mut code = String::new();
+    write_file(&mut code, program, playfield).expect("writing to a string should not fail");
+    code
+}
+
+/// Writes a C source file.
+fn write_file(f: &mut impl Write, program: &Program, playfield: &Playfield) -> fmt::Result {
+    let (width, height) = playfield.bounds();
+    writeln!(f, "/* Generated by Fungus from a Befunge program. */")?;
+    f.write_str(HEADER)?;
+    writeln!(f)?;
+    writeln!(f, "#define WIDTH {width}")?;
+    writeln!(f, "#define HEIGHT {height}")?;
+    writeln!(
+        f,
+        "#define WRAP {}",
+        u8::from(playfield.standard().wraps_coordinates())
+    )?;
+    writeln!(f)?;
+    writeln!(f, "/* The playfield. */")?;
+    writeln!(f, "static int32_t playfield[WIDTH * HEIGHT] = {{")?;
+
+    for y in 0..height {
+        let row: Vec<String> = (0..width)
+            .map(|x| {
+                let value = playfield.get(x, y).unwrap_or_default().into_i32();
+                c_int(value)
+            })
+            .collect();
+
+        writeln!(f, "    {},", row.join(", "))?;
+    }
+
+    writeln!(f, "}};")?;
+    f.write_str(RUNTIME)?;
+    writeln!(f)?;
+    writeln!(f, "int main(void) {{")?;
+    writeln!(f, "    srand((unsigned int)time(NULL));")?;
+    writeln!(f, "#ifdef SIGPIPE")?;
+    writeln!(
+        f,
+        "    /* A closed output is detected by write errors instead. */"
+    )?;
+    writeln!(f, "    signal(SIGPIPE, SIG_IGN);")?;
+    writeln!(f, "#endif")?;
+    writeln!(f, "    goto {};", label_name(&Label::Main))?;
+
+    for (label, block) in &program.blocks {
+        writeln!(f)?;
+        write_block(f, label, block)?;
+    }
+
+    // The end label is omitted if it is unused to avoid compiler warnings.
+    if program.blocks.values().any(can_end) {
+        writeln!(f)?;
+        writeln!(f, "end:")?;
+        writeln!(f, "    return finish();")?;
+    }
+
+    writeln!(f, "}}")
+}
+
+/// Returns whether a block can jump to the end of the program.
+fn can_end(block: &Block) -> bool {
+    matches!(block.exit, Exit::End)
+        || block.instructions.iter().any(|i| {
+            matches!(
+                i,
+                Instruction::Put(_) | Instruction::PutAtGuarded(_, _, _, _)
+            )
+        })
+}
+
+/// Writes a labeled block.
+fn write_block(f: &mut impl Write, label: &Label, block: &Block) -> fmt::Result {
+    writeln!(f, "{}:", label_name(label))?;
+
+    for instruction in &block.instructions {
+        write_instruction(f, instruction)?;
+    }
+
+    match &block.exit {
+        Exit::Jump(l) => writeln!(f, "    goto {};", label_name(l)),
+        Exit::Random(r, d, l, u) => {
+            writeln!(f, "    switch (random_direction()) {{")?;
+            writeln!(f, "    case 0: goto {};", label_name(r))?;
+            writeln!(f, "    case 1: goto {};", label_name(d))?;
+            writeln!(f, "    case 2: goto {};", label_name(l))?;
+            writeln!(f, "    default: goto {};", label_name(u))?;
+            writeln!(f, "    }}")
+        }
+        Exit::Branch(t, e) => {
+            writeln!(f, "    if (pop() != 0) goto {};", label_name(t))?;
+            writeln!(f, "    goto {};", label_name(e))
+        }
+        Exit::End => writeln!(f, "    goto end;"),
+    }
+}
+
+/// Writes an instruction as a C statement.
+fn write_instruction(f: &mut impl Write, instruction: &Instruction) -> fmt::Result {
+    match instruction {
+        Instruction::Push(v) => writeln!(f, "    push({});", c_int(v.into_i32())),
+        Instruction::Unary(UnOp::Not) => writeln!(f, "    push(pop() == 0);"),
+        Instruction::Binary(o) => writeln!(f, "    binary({});", bin_op_name(*o)),
+        Instruction::Divide(o) => {
+            let name = match o {
+                DivOp::Quotient => "OP_DIVIDE",
+                DivOp::Remainder => "OP_MODULO",
+            };
+            writeln!(f, "    divide({name});")
+        }
+        Instruction::Duplicate => writeln!(f, "    push(peek());"),
+        Instruction::Swap => writeln!(f, "    swap();"),
+        Instruction::Pop => writeln!(f, "    (void)pop();"),
+        Instruction::OutputInt => writeln!(f, "    output_int(pop());"),
+        Instruction::OutputChar => writeln!(f, "    output_char(pop());"),
+        Instruction::Get => writeln!(f, "    get();"),
+        Instruction::Put(s) => {
+            writeln!(f, "    if (put()) {{")?;
+            writeln!(f, "        interpret({});", state_args(s))?;
+            writeln!(f, "        goto end;")?;
+            writeln!(f, "    }}")
+        }
+        Instruction::InputInt => writeln!(f, "    push(input_int());"),
+        Instruction::InputChar => writeln!(f, "    push(input_char());"),
+        Instruction::Print(s) => writeln!(f, "    print({}, {});", c_string(s), s.len()),
+        Instruction::GetAt(x, y) => writeln!(f, "    push(playfield[{x} + {y} * WIDTH]);"),
+        Instruction::PutAt(x, y) => writeln!(f, "    playfield[{x} + {y} * WIDTH] = pop();"),
+        Instruction::PutData => writeln!(f, "    (void)put();"),
+        Instruction::PutAtGuarded(x, y, m, s) => {
+            writeln!(f, "    if (put_at_guarded({x}, {y}, {})) {{", mode_name(*m))?;
+            writeln!(f, "        interpret({});", state_args(s))?;
+            writeln!(f, "        goto end;")?;
+            writeln!(f, "    }}")
+        }
+    }
+}
+
+/// Returns the C label name for a label.
+fn label_name(label: &Label) -> String {
+    format!("label_{label}")
+}
+
+/// Returns the C arguments for interpreting from a state.
+fn state_args(state: &State) -> String {
+    let direction = match state.direction {
+        Direction::Right => "DIR_RIGHT",
+        Direction::Down => "DIR_DOWN",
+        Direction::Left => "DIR_LEFT",
+        Direction::Up => "DIR_UP",
+    };
+
+    let mode = mode_name(state.mode);
+    format!("{}, {}, {mode}, {direction}", state.x, state.y)
+}
+
+/// Returns the C constant name for a mode.
+fn mode_name(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Command => "MODE_COMMAND",
+        Mode::String => "MODE_STRING",
+    }
+}
+
+/// Returns the C operator constant name for a binary operator.
+fn bin_op_name(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "OP_ADD",
+        BinOp::Subtract => "OP_SUBTRACT",
+        BinOp::Multiply => "OP_MULTIPLY",
+        BinOp::Greater => "OP_GREATER",
+        BinOp::Divide => "OP_DIVIDE",
+        BinOp::Modulo => "OP_MODULO",
+    }
+}
+
+/// Returns a C integer literal for an `int32_t` value.
+fn c_int(value: i32) -> String {
+    if value == i32::MIN {
+        // `-2147483648` is the negation of an out-of-range literal in C.
+        "INT32_MIN".into()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Returns a C string literal containing the UTF-8 bytes of a string.
+fn c_string(value: &str) -> String {
+    let mut literal = String::from('"');
+    for byte in value.bytes() {
+        match byte {
+            b'"' | b'\\' | b'?' => {
+                literal.push('\\');
+                literal.push(byte.into());
+            }
+            b' '..=b'~' => literal.push(byte.into()),
+            _ => literal.push_str(&format!("\\{byte:03o}")),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// The includes at the start of a C source file.
+const HEADER: &str = r"
+#include <errno.h>
+#include <signal.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+";
+
+/// The runtime support code for a C source file.
+const RUNTIME: &str = r#"
+enum { MODE_COMMAND, MODE_STRING };
+enum { DIR_RIGHT, DIR_DOWN, DIR_LEFT, DIR_UP };
+enum { OP_ADD, OP_SUBTRACT, OP_MULTIPLY, OP_GREATER, OP_DIVIDE, OP_MODULO };
+
+/* The stack. */
+static int32_t *stack = NULL;
+static size_t stack_length = 0;
+static size_t stack_capacity = 0;
+
+/* The character input buffer. */
+static int32_t input_chars[4096];
+static size_t input_chars_length = 0;
+static size_t input_chars_index = 0;
+
+static inline void push(int32_t value) {
+    if (stack_length == stack_capacity) {
+        stack_capacity = stack_capacity ? stack_capacity * 2 : 256;
+        stack = realloc(stack, stack_capacity * sizeof *stack);
+        if (!stack) {
+            fputs("error: out of memory\n", stderr);
+            exit(1);
+        }
+    }
+    stack[stack_length++] = value;
+}
+
+static inline int32_t pop(void) {
+    return stack_length ? stack[--stack_length] : 0;
+}
+
+static inline int32_t peek(void) {
+    return stack_length ? stack[stack_length - 1] : 0;
+}
+
+static inline void swap(void) {
+    int32_t top = pop();
+    int32_t under = pop();
+    push(top);
+    push(under);
+}
+
+/* Evaluates a binary operator with wrapping 32-bit arithmetic. */
+static inline int32_t eval(int op, int32_t lhs, int32_t rhs) {
+    uint32_t l = (uint32_t)lhs, r = (uint32_t)rhs;
+    switch (op) {
+    case OP_ADD: return (int32_t)(l + r);
+    case OP_SUBTRACT: return (int32_t)(l - r);
+    case OP_MULTIPLY: return (int32_t)(l * r);
+    case OP_GREATER: return lhs > rhs;
+    case OP_DIVIDE: return (lhs == INT32_MIN && rhs == -1) ? INT32_MIN : lhs / rhs;
+    default: return (lhs == INT32_MIN && rhs == -1) ? 0 : lhs % rhs;
+    }
+}
+
+static inline void binary(int op) {
+    int32_t rhs = pop();
+    int32_t lhs = pop();
+    push(eval(op, lhs, rhs));
+}
+
+/* Exits if the output could not be written. If the output was closed, then the
+ * program is stopped quietly, like Fungus does. */
+static inline void check_output(void) {
+    if (ferror(stdout)) {
+        exit(errno == EPIPE ? 0 : 4);
+    }
+}
+
+static inline void output_char(int32_t value) {
+    uint32_t c = (uint32_t)value;
+    unsigned char bytes[4];
+    size_t length;
+    if (c > 0x10ffff || (c >= 0xd800 && c <= 0xdfff)) {
+        c = 0xfffd;
+    }
+    if (c < 0x80) {
+        bytes[0] = (unsigned char)c;
+        length = 1;
+    } else if (c < 0x800) {
+        bytes[0] = (unsigned char)(0xc0 | c >> 6);
+        bytes[1] = (unsigned char)(0x80 | (c & 0x3f));
+        length = 2;
+    } else if (c < 0x10000) {
+        bytes[0] = (unsigned char)(0xe0 | c >> 12);
+        bytes[1] = (unsigned char)(0x80 | (c >> 6 & 0x3f));
+        bytes[2] = (unsigned char)(0x80 | (c & 0x3f));
+        length = 3;
+    } else {
+        bytes[0] = (unsigned char)(0xf0 | c >> 18);
+        bytes[1] = (unsigned char)(0x80 | (c >> 12 & 0x3f));
+        bytes[2] = (unsigned char)(0x80 | (c >> 6 & 0x3f));
+        bytes[3] = (unsigned char)(0x80 | (c & 0x3f));
+        length = 4;
+    }
+    fwrite(bytes, 1, length, stdout);
+    check_output();
+}
+
+static inline void output_int(int32_t value) {
+    printf("%ld ", (long)value);
+    check_output();
+}
+
+static inline void print(const char *string, size_t length) {
+    fwrite(string, 1, length, stdout);
+    check_output();
+}
+
+/* Reads a line of user input into a buffer and returns its length. */
+static inline size_t read_line(unsigned char *line, size_t capacity) {
+    size_t length = 0;
+    int c;
+    fflush(stdout);
+    check_output();
+    while (length < capacity && (c = getchar()) != EOF) {
+        line[length++] = (unsigned char)c;
+        if (c == '\n') {
+            break;
+        }
+    }
+    return length;
+}
+
+static inline int32_t input_char(void) {
+    if (input_chars_index == input_chars_length) {
+        unsigned char line[4096];
+        size_t length = read_line(line, sizeof line);
+        size_t i = 0;
+        input_chars_length = 0;
+        input_chars_index = 0;
+        while (i < length) {
+            unsigned char b = line[i];
+            int32_t c;
+            size_t extra;
+            if (b < 0x80) {
+                c = b;
+                extra = 0;
+            } else if ((b & 0xe0) == 0xc0) {
+                c = b & 0x1f;
+                extra = 1;
+            } else if ((b & 0xf0) == 0xe0) {
+                c = b & 0x0f;
+                extra = 2;
+            } else {
+                c = b & 0x07;
+                extra = 3;
+            }
+            i++;
+            while (extra-- > 0 && i < length) {
+                c = c << 6 | (line[i++] & 0x3f);
+            }
+            input_chars[input_chars_length++] = c;
+        }
+    }
+    return input_chars_index < input_chars_length ? input_chars[input_chars_index++] : -1;
+}
+
+static inline int32_t input_int(void) {
+    unsigned char line[4096];
+    size_t length = read_line(line, sizeof line);
+    size_t start = 0;
+    int negative = 0;
+    int64_t value = 0;
+    while (start < length && strchr(" \t\n\v\f\r", line[start])) {
+        start++;
+    }
+    while (length > start && strchr(" \t\n\v\f\r", line[length - 1])) {
+        length--;
+    }
+    if (start < length && (line[start] == '+' || line[start] == '-')) {
+        negative = line[start++] == '-';
+    }
+    if (start == length) {
+        return -1;
+    }
+    for (; start < length; start++) {
+        if (line[start] < '0' || line[start] > '9') {
+            return -1;
+        }
+        value = value * 10 + (line[start] - '0');
+        if (value > (int64_t)INT32_MAX + negative) {
+            return -1;
+        }
+    }
+    return (int32_t)(negative ? -value : value);
+}
+
+static inline void divide(int op) {
+    int32_t rhs = pop();
+    int32_t lhs = pop();
+    if (rhs != 0) {
+        push(eval(op, lhs, rhs));
+    } else {
+        printf("What do you want %ld%c0 to be? ", (long)lhs, op == OP_DIVIDE ? '/' : '%');
+        check_output();
+        push(input_int());
+    }
+}
+
+/* Wraps get and put coordinates around the playfield if WRAP is set. */
+static inline void wrap(int32_t *x, int32_t *y) {
+    if (WRAP) {
+        *x = (*x % WIDTH + WIDTH) % WIDTH;
+        *y = (*y % HEIGHT + HEIGHT) % HEIGHT;
+    }
+}
+
+static inline void get(void) {
+    int32_t y = pop();
+    int32_t x = pop();
+    wrap(&x, &y);
+    if (x >= 0 && x < WIDTH && y >= 0 && y < HEIGHT) {
+        push(playfield[x + y * WIDTH]);
+    } else {
+        push(0);
+    }
+}
+
+/* Pops a position and a value and stores the value in the playfield. Returns
+ * whether the value of an in-bounds cell was changed. */
+static inline int put(void) {
+    int32_t y = pop();
+    int32_t x = pop();
+    int32_t value = pop();
+    int32_t *cell;
+    wrap(&x, &y);
+    if (x < 0 || x >= WIDTH || y < 0 || y >= HEIGHT) {
+        return 0;
+    }
+    cell = &playfield[x + y * WIDTH];
+    if (*cell == value) {
+        return 0;
+    }
+    *cell = value;
+    return 1;
+}
+
+/* Returns whether a value is parsed as a command in command mode. */
+static inline int is_command(int32_t value) {
+    return value > 0 && value < 0x80
+        && strchr("0123456789+-*/%!`><^v?_|\":\\$.,#gp&~@", (int)value) != NULL;
+}
+
+/* Pops a value and stores it in the playfield at an in-bounds position that is
+ * reached in a mode. Returns whether the value changes how the cell is parsed
+ * in the mode. */
+static inline int put_at_guarded(int x, int y, int mode) {
+    int32_t value = pop();
+    int32_t *cell = &playfield[x + y * WIDTH];
+    int32_t previous_value = *cell;
+    *cell = value;
+    if (previous_value == value) {
+        return 0;
+    }
+    return mode == MODE_STRING || is_command(previous_value) || is_command(value);
+}
+
+static inline int random_direction(void) {
+    return rand() & 3;
+}
+
+/* Interprets the playfield directly from a program counter state. This is used
+ * after the playfield is modified in a way that may change the program. */
+static inline void interpret(int x, int y, int mode, int direction) {
+    for (;;) {
+        int32_t value = playfield[x + y * WIDTH];
+        if (mode == MODE_STRING) {
+            if (value == '"') {
+                mode = MODE_COMMAND;
+            } else {
+                push(value);
+            }
+        } else {
+            switch (value) {
+            case '0': case '1': case '2': case '3': case '4':
+            case '5': case '6': case '7': case '8': case '9':
+                push(value - '0');
+                break;
+            case '+': binary(OP_ADD); break;
+            case '-': binary(OP_SUBTRACT); break;
+            case '*': binary(OP_MULTIPLY); break;
+            case '/': divide(OP_DIVIDE); break;
+            case '%': divide(OP_MODULO); break;
+            case '!': push(pop() == 0); break;
+            case '`': binary(OP_GREATER); break;
+            case '>': direction = DIR_RIGHT; break;
+            case 'v': direction = DIR_DOWN; break;
+            case '<': direction = DIR_LEFT; break;
+            case '^': direction = DIR_UP; break;
+            case '?': direction = random_direction(); break;
+            case '_': direction = pop() != 0 ? DIR_LEFT : DIR_RIGHT; break;
+            case '|': direction = pop() != 0 ? DIR_UP : DIR_DOWN; break;
+            case '"': mode = MODE_STRING; break;
+            case ':': push(peek()); break;
+            case '\\': swap(); break;
+            case '$': (void)pop(); break;
+            case '.': output_int(pop()); break;
+            case ',': output_char(pop()); break;
+            case '#':
+                switch (direction) {
+                case DIR_RIGHT: x = (x + 1) % WIDTH; break;
+                case DIR_DOWN: y = (y + 1) % HEIGHT; break;
+                case DIR_LEFT: x = (x + WIDTH - 1) % WIDTH; break;
+                default: y = (y + HEIGHT - 1) % HEIGHT; break;
+                }
+                break;
+            case 'g': get(); break;
+            case 'p': (void)put(); break;
+            case '&': push(input_int()); break;
+            case '~': push(input_char()); break;
+            case '@': return;
+            default: break;
+            }
+        }
+        switch (direction) {
+        case DIR_RIGHT: x = (x + 1) % WIDTH; break;
+        case DIR_DOWN: y = (y + 1) % HEIGHT; break;
+        case DIR_LEFT: x = (x + WIDTH - 1) % WIDTH; break;
+        default: y = (y + HEIGHT - 1) % HEIGHT; break;
+        }
+    }
+}
+
+/* Flushes the output and returns an exit code. */
+static inline int finish(void) {
+    fflush(stdout);
+    check_output();
+    return 0;
+}
+"#;
@@ -0,0 +1,40 @@
+use std::{
+    error,
+    fmt::{self, Display, Formatter},
+    result,
+};
+
+/// A result that may contain a code generation error.
+pub type Result<T> = result::Result<T, Error>;
+
+/// An error raised while generating source code.
+#[derive(Debug)]
+pub enum Error {
+    /// An error caused by a program with get or put instructions whose
+    /// positions are not known, or with puts that may modify the program,
+    /// which depend on the original playfield layout.
+    DynamicPlayfield,
+
+    /// An error caused by a generated playfield with a width and height that
+    /// are larger than a Befunge-93 playfield.
+    PlayfieldTooLarge(usize, usize),
+}
+
+impl error::Error for Error {}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DynamicPlayfield => f.write_str(
+                "program has get or put commands with unknown positions or that may modify \
+                 the program, so its playfield cannot be rearranged",
+            ),
+            Self::PlayfieldTooLarge(w, h) => {
+                write!(
+                    f,
+                    "generated playfield is {w}x{h}, which is larger than 80x25"
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,41 @@
+mod befunge;
+mod c;
+mod error;
+mod rust;
+mod wat;
+
+pub use error::{Error, Result};
+
+use crate::{common::Playfield, ir::Program};
+
+/// A target language for generating source code.
+#[derive(Clone, Copy)]
+pub enum Target {
+    /// A standalone C source file.
+    C,
+
+    /// A self-contained Rust module.
+    Rust,
+
+    /// A WebAssembly text module.
+    Wat,
+
+    /// A Befunge-93 playfield.
+    Befunge,
+}
+
+/// Generates source code for a program and its playfield in a target language.
+pub fn generate_program(
+    program: &Program,
+    playfield: &Playfield,
+    target: Target,
+) -> Result<String> {
+    let code = match target {
+        Target::C => c::generate_program(program, playfield),
+        Target::Rust => rust::generate_program(program, playfield),
+        Target::Wat => wat::generate_program(program, playfield),
+        Target::Befunge => befunge::generate_program(program, playfield)?,
+    };
+
+    Ok(code)
+}
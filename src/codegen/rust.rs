@@ -0,0 +1,566 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+};
+
+use crate::{
+    common::Playfield,
+    ir::{
+        Block, Exit, Instruction, Label, Program, State,
+        ops::{BinOp, UnOp},
+        state::{Direction, Mode},
+    },
+};
+
+/// Generates a self-contained Rust module from a program and its playfield.
+pub fn generate_program(program: &Program, playfield: &Playfield) -> String {
+    let mut code = String::new();
+    write_module(&mut code, program, playfield).expect("writing to a string should not fail");
+    code
+}
+
+/// Writes a Rust module.
+fn write_module(f: &mut impl Write, program: &Program, playfield: &Playfield) -> fmt::Result {
+    let (width, height) = playfield.bounds();
+    let indices: BTreeMap<&Label, usize> = program
+        .blocks
+        .keys()
+        .enumerate()
+        .map(|(i, l)| (l, i))
+        .collect();
+
+    writeln!(f, "//! Generated by Fungus from a Befunge program.")?;
+    f.write_str(HEADER)?;
+    writeln!(f)?;
+    writeln!(f, "/// The width of the playfield in cells.")?;
+    writeln!(f, "const WIDTH: usize = {width};")?;
+    writeln!(f)?;
+    writeln!(f, "/// The height of the playfield in cells.")?;
+    writeln!(f, "const HEIGHT: usize = {height};")?;
+    writeln!(f)?;
+    writeln!(
+        f,
+        "/// Whether get and put coordinates wrap around the playfield."
+    )?;
+    writeln!(
+        f,
+        "const WRAP: bool = {};",
+        playfield.standard().wraps_coordinates()
+    )?;
+    writeln!(f)?;
+    writeln!(f, "/// The initial playfield.")?;
+    writeln!(f, "static PLAYFIELD: [i32; WIDTH * HEIGHT] = [")?;
+
+    for y in 0..height {
+        let row: Vec<String> = (0..width)
+            .map(|x| {
+                let value = playfield.get(x, y).unwrap_or_default();
+                value.into_i32().to_string()
+            })
+            .collect();
+
+        writeln!(f, "    {},", row.join(", "))?;
+    }
+
+    writeln!(f, "];")?;
+    f.write_str(RUNTIME)?;
+    writeln!(f)?;
+    writeln!(f, "impl Machine {{")?;
+    writeln!(f, "    /// Runs the compiled program.")?;
+    writeln!(f, "    // A program may never jump between blocks.")?;
+    writeln!(
+        f,
+        "    #[allow(unreachable_code, unused_mut, clippy::never_loop)]"
+    )?;
+    writeln!(f, "    fn run(&mut self) -> io::Result<()> {{")?;
+    writeln!(f, "        let mut block = {};", indices[&Label::Main])?;
+    writeln!(f, "        loop {{")?;
+    writeln!(f, "            block = match block {{")?;
+
+    for (label, block) in &program.blocks {
+        write_block(f, indices[label], label, block, &indices)?;
+    }
+
+    writeln!(f, "                _ => unreachable!(),")?;
+    writeln!(f, "            }};")?;
+    writeln!(f, "        }}")?;
+    writeln!(f, "    }}")?;
+    writeln!(f, "}}")
+}
+
+/// The indentation of statements in a generated match arm.
+const INDENT: &str = "                    ";
+
+/// Writes a block as a match arm that evaluates to the next block index.
+fn write_block(
+    f: &mut impl Write,
+    index: usize,
+    label: &Label,
+    block: &Block,
+    indices: &BTreeMap<&Label, usize>,
+) -> fmt::Result {
+    writeln!(f, "                // {label}")?;
+    writeln!(f, "                {index} => {{")?;
+
+    for instruction in &block.instructions {
+        write_instruction(f, instruction)?;
+    }
+
+    match &block.exit {
+        Exit::Jump(l) => writeln!(f, "{INDENT}{}", indices[l])?,
+        Exit::Random(r, d, l, u) => {
+            writeln!(f, "{INDENT}match self.random_direction() {{")?;
+            for (direction, label) in [("Right", r), ("Down", d), ("Left", l), ("Up", u)] {
+                writeln!(
+                    f,
+                    "{INDENT}    Direction::{direction} => {},",
+                    indices[label]
+                )?;
+            }
+            writeln!(f, "{INDENT}}}")?;
+        }
+        Exit::Branch(t, e) => writeln!(
+            f,
+            "{INDENT}if self.pop() != 0 {{ {} }} else {{ {} }}",
+            indices[t], indices[e]
+        )?,
+        Exit::End => writeln!(f, "{INDENT}return Ok(());")?,
+    }
+
+    writeln!(f, "                }}")
+}
+
+/// Writes an instruction as a Rust statement.
+fn write_instruction(f: &mut impl Write, instruction: &Instruction) -> fmt::Result {
+    match instruction {
+        Instruction::Push(v) => writeln!(f, "{INDENT}self.push({});", v.into_i32()),
+        Instruction::Unary(UnOp::Not) => writeln!(f, "{INDENT}self.not();"),
+        Instruction::Binary(o) => writeln!(f, "{INDENT}self.binary(BinOp::{});", bin_op_name(*o)),
+        Instruction::Divide(o) => {
+            let name = bin_op_name((*o).into());
+            writeln!(f, "{INDENT}self.divide(BinOp::{name})?;")
+        }
+        Instruction::Duplicate => writeln!(f, "{INDENT}self.push(self.peek());"),
+        Instruction::Swap => writeln!(f, "{INDENT}self.swap();"),
+        Instruction::Pop => writeln!(f, "{INDENT}self.pop();"),
+        Instruction::OutputInt => writeln!(f, "{INDENT}self.output_int()?;"),
+        Instruction::OutputChar => writeln!(f, "{INDENT}self.output_char()?;"),
+        Instruction::Get => writeln!(f, "{INDENT}self.get();"),
+        Instruction::Put(s) => {
+            writeln!(f, "{INDENT}if self.put() {{")?;
+            writeln!(f, "{INDENT}    return self.interpret({});", state_args(s))?;
+            writeln!(f, "{INDENT}}}")
+        }
+        Instruction::InputInt => writeln!(f, "{INDENT}self.input_int()?;"),
+        Instruction::InputChar => writeln!(f, "{INDENT}self.input_char()?;"),
+        Instruction::Print(s) => writeln!(f, "{INDENT}self.output_str({s:?})?;"),
+        Instruction::GetAt(x, y) => writeln!(f, "{INDENT}self.get_at({x}, {y});"),
+        Instruction::PutAt(x, y) => writeln!(f, "{INDENT}self.put_at({x}, {y});"),
+        Instruction::PutData => writeln!(f, "{INDENT}self.put();"),
+        Instruction::PutAtGuarded(x, y, m, s) => {
+            let mode = mode_name(*m);
+            writeln!(
+                f,
+                "{INDENT}if self.put_at_guarded({x}, {y}, Mode::{mode}) {{"
+            )?;
+            writeln!(f, "{INDENT}    return self.interpret({});", state_args(s))?;
+            writeln!(f, "{INDENT}}}")
+        }
+    }
+}
+
+/// Returns the Rust arguments for interpreting from a state.
+fn state_args(state: &State) -> String {
+    let mode = mode_name(state.mode);
+    let direction = match state.direction {
+        Direction::Right => "Right",
+        Direction::Down => "Down",
+        Direction::Left => "Left",
+        Direction::Up => "Up",
+    };
+
+    format!(
+        "{}, {}, Mode::{mode}, Direction::{direction}",
+        state.x, state.y
+    )
+}
+
+/// Returns the generated variant name for a mode.
+fn mode_name(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Command => "Command",
+        Mode::String => "String",
+    }
+}
+
+/// Returns the generated variant name for a binary operator.
+fn bin_op_name(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "Add",
+        BinOp::Subtract => "Subtract",
+        BinOp::Multiply => "Multiply",
+        BinOp::Greater => "Greater",
+        BinOp::Divide => "Divide",
+        BinOp::Modulo => "Modulo",
+    }
+}
+
+/// The imports at the start of a Rust module.
+const HEADER: &str = r"
+use std::{
+    collections::{VecDeque, hash_map::RandomState},
+    hash::{BuildHasher, Hasher},
+    io::{self, Write},
+};
+";
+
+/// The runtime support code for a Rust module.
+const RUNTIME: &str = r#"
+/// Runs the program with the standard input and output streams.
+pub fn run() -> io::Result<()> {
+    let mut machine = Machine {
+        playfield: PLAYFIELD.to_vec(),
+        stack: Vec::new(),
+        input_chars: VecDeque::new(),
+        random_state: RandomState::new().build_hasher().finish() | 1,
+    };
+
+    let result = machine.run();
+    let flushed = io::stdout().flush();
+    result.and(flushed)
+}
+
+/// A program counter mode.
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+enum Mode {
+    /// A mode where playfield values are executed as commands.
+    Command,
+
+    /// A mode where playfield values are pushed to the stack.
+    String,
+}
+
+/// A program counter direction.
+#[derive(Clone, Copy)]
+enum Direction {
+    /// A direction towards the right edge of the playfield.
+    Right,
+
+    /// A direction towards the bottom edge of the playfield.
+    Down,
+
+    /// A direction towards the left edge of the playfield.
+    Left,
+
+    /// A direction towards the top edge of the playfield.
+    Up,
+}
+
+/// A binary operator.
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+enum BinOp {
+    /// Addition.
+    Add,
+
+    /// Subtraction.
+    Subtract,
+
+    /// Multiplication.
+    Multiply,
+
+    /// Greater than comparison.
+    Greater,
+
+    /// Division.
+    Divide,
+
+    /// Modulo.
+    Modulo,
+}
+
+impl BinOp {
+    /// Evaluates the binary operator with wrapping arithmetic.
+    fn eval(self, lhs: i32, rhs: i32) -> i32 {
+        match self {
+            Self::Add => lhs.wrapping_add(rhs),
+            Self::Subtract => lhs.wrapping_sub(rhs),
+            Self::Multiply => lhs.wrapping_mul(rhs),
+            Self::Greater => (lhs > rhs).into(),
+            Self::Divide => lhs.wrapping_div(rhs),
+            Self::Modulo => lhs.wrapping_rem(rhs),
+        }
+    }
+}
+
+/// The state of a running program.
+struct Machine {
+    /// The playfield.
+    playfield: Vec<i32>,
+
+    /// The stack.
+    stack: Vec<i32>,
+
+    /// The character input buffer.
+    input_chars: VecDeque<char>,
+
+    /// The state of the random number generator.
+    random_state: u64,
+}
+
+#[allow(dead_code)]
+impl Machine {
+    /// Pushes a value to the stack.
+    fn push(&mut self, value: i32) {
+        self.stack.push(value);
+    }
+
+    /// Pops a value from the stack.
+    fn pop(&mut self) -> i32 {
+        self.stack.pop().unwrap_or_default()
+    }
+
+    /// Returns the top value of the stack.
+    fn peek(&self) -> i32 {
+        self.stack.last().copied().unwrap_or_default()
+    }
+
+    /// Swaps the top two values of the stack.
+    fn swap(&mut self) {
+        let top = self.pop();
+        let under = self.pop();
+        self.push(top);
+        self.push(under);
+    }
+
+    /// Applies logical not to the top value of the stack.
+    fn not(&mut self) {
+        let rhs = self.pop();
+        self.push((rhs == 0).into());
+    }
+
+    /// Applies a binary operator to the top two values of the stack.
+    fn binary(&mut self, op: BinOp) {
+        let rhs = self.pop();
+        let lhs = self.pop();
+        self.push(op.eval(lhs, rhs));
+    }
+
+    /// Applies a division operator to the top two values of the stack. The
+    /// user is asked for the result if the right-hand operand is zero.
+    fn divide(&mut self, op: BinOp) -> io::Result<()> {
+        let rhs = self.pop();
+        let lhs = self.pop();
+        if rhs != 0 {
+            self.push(op.eval(lhs, rhs));
+            Ok(())
+        } else {
+            let symbol = if let BinOp::Divide = op { '/' } else { '%' };
+            self.output_str(&format!("What do you want {lhs}{symbol}0 to be? "))?;
+            self.input_int()
+        }
+    }
+
+    /// Pops a value from the stack and outputs it as an integer.
+    fn output_int(&mut self) -> io::Result<()> {
+        let value = self.pop();
+        self.output_str(&format!("{value} "))
+    }
+
+    /// Pops a value from the stack and outputs it as a character.
+    fn output_char(&mut self) -> io::Result<()> {
+        let value = self.pop();
+        let value = char::from_u32(0u32.wrapping_add_signed(value))
+            .unwrap_or(char::REPLACEMENT_CHARACTER);
+        self.output_str(value.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Outputs a string.
+    fn output_str(&mut self, value: &str) -> io::Result<()> {
+        io::stdout().write_all(value.as_bytes())
+    }
+
+    /// Pushes an integer from a line of user input to the stack.
+    fn input_int(&mut self) -> io::Result<()> {
+        let value = self.read_line()?.trim().parse().unwrap_or(-1);
+        self.push(value);
+        Ok(())
+    }
+
+    /// Pushes a character from user input to the stack.
+    fn input_char(&mut self) -> io::Result<()> {
+        if self.input_chars.is_empty() {
+            let line = self.read_line()?;
+            self.input_chars.extend(line.chars());
+        }
+
+        let value = self.input_chars.pop_front().map_or(-1, |c| c as i32);
+        self.push(value);
+        Ok(())
+    }
+
+    /// Reads a line of user input.
+    fn read_line(&mut self) -> io::Result<String> {
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        io::stdin().read_line(&mut line)?;
+        Ok(line)
+    }
+
+    /// Pops coordinates from the stack and pushes the value from the playfield
+    /// at the coordinates to the stack.
+    fn get(&mut self) {
+        let y = self.pop();
+        let x = self.pop();
+        let value = match cell_index(x, y) {
+            Some(i) => self.playfield[i],
+            None => 0,
+        };
+        self.push(value);
+    }
+
+    /// Pops coordinates and a value from the stack and stores the value in the
+    /// playfield at the coordinates. Returns whether an in-bounds value was
+    /// changed.
+    fn put(&mut self) -> bool {
+        let y = self.pop();
+        let x = self.pop();
+        let value = self.pop();
+        match cell_index(x, y) {
+            Some(i) if self.playfield[i] != value => {
+                self.playfield[i] = value;
+                true
+            }
+            Some(_) | None => false,
+        }
+    }
+
+    /// Pushes the value from the playfield at in-bounds coordinates to the
+    /// stack.
+    fn get_at(&mut self, x: usize, y: usize) {
+        self.push(self.playfield[x + y * WIDTH]);
+    }
+
+    /// Pops a value from the stack and stores it in the playfield at in-bounds
+    /// coordinates.
+    fn put_at(&mut self, x: usize, y: usize) {
+        self.playfield[x + y * WIDTH] = self.pop();
+    }
+
+    /// Pops a value from the stack and stores it in the playfield at in-bounds
+    /// coordinates that are reached in a mode. Returns whether the value
+    /// changes how the cell is parsed in the mode.
+    fn put_at_guarded(&mut self, x: usize, y: usize, mode: Mode) -> bool {
+        let value = self.pop();
+        let previous_value = std::mem::replace(&mut self.playfield[x + y * WIDTH], value);
+        previous_value != value
+            && (matches!(mode, Mode::String) || is_command(previous_value) || is_command(value))
+    }
+
+    /// Returns the next random direction.
+    fn random_direction(&mut self) -> Direction {
+        self.random_state ^= self.random_state << 13;
+        self.random_state ^= self.random_state >> 7;
+        self.random_state ^= self.random_state << 17;
+
+        match self.random_state >> 32 & 0b11 {
+            0b00 => Direction::Right,
+            0b01 => Direction::Down,
+            0b10 => Direction::Left,
+            _ => Direction::Up,
+        }
+    }
+
+    /// Interprets the playfield directly from a program counter state. This is
+    /// used after the playfield is modified in a way that may change the
+    /// program.
+    fn interpret(
+        &mut self,
+        mut x: usize,
+        mut y: usize,
+        mut mode: Mode,
+        mut direction: Direction,
+    ) -> io::Result<()> {
+        loop {
+            let value = self.playfield[x + y * WIDTH];
+            let command = char::from_u32(0u32.wrapping_add_signed(value))
+                .unwrap_or(char::REPLACEMENT_CHARACTER);
+
+            match (mode, command) {
+                (Mode::Command, '0'..='9') => self.push(value - i32::from(b'0')),
+                (Mode::Command, '+') => self.binary(BinOp::Add),
+                (Mode::Command, '-') => self.binary(BinOp::Subtract),
+                (Mode::Command, '*') => self.binary(BinOp::Multiply),
+                (Mode::Command, '/') => self.divide(BinOp::Divide)?,
+                (Mode::Command, '%') => self.divide(BinOp::Modulo)?,
+                (Mode::Command, '!') => self.not(),
+                (Mode::Command, '`') => self.binary(BinOp::Greater),
+                (Mode::Command, '>') => direction = Direction::Right,
+                (Mode::Command, '<') => direction = Direction::Left,
+                (Mode::Command, '^') => direction = Direction::Up,
+                (Mode::Command, 'v') => direction = Direction::Down,
+                (Mode::Command, '?') => direction = self.random_direction(),
+                (Mode::Command, '_') if self.pop() != 0 => direction = Direction::Left,
+                (Mode::Command, '_') => direction = Direction::Right,
+                (Mode::Command, '|') if self.pop() != 0 => direction = Direction::Up,
+                (Mode::Command, '|') => direction = Direction::Down,
+                (Mode::Command, '"') => mode = Mode::String,
+                (Mode::String, '"') => mode = Mode::Command,
+                (Mode::Command, ':') => self.push(self.peek()),
+                (Mode::Command, '\\') => self.swap(),
+                (Mode::Command, '$') => {
+                    self.pop();
+                }
+                (Mode::Command, '.') => self.output_int()?,
+                (Mode::Command, ',') => self.output_char()?,
+                (Mode::Command, '#') => (x, y) = step(x, y, direction),
+                (Mode::Command, 'g') => self.get(),
+                (Mode::Command, 'p') => {
+                    self.put();
+                }
+                (Mode::Command, '&') => self.input_int()?,
+                (Mode::Command, '~') => self.input_char()?,
+                (Mode::Command, '@') => return Ok(()),
+                (Mode::Command, _) => {}
+                (Mode::String, _) => self.push(value),
+            }
+
+            (x, y) = step(x, y, direction);
+        }
+    }
+}
+
+/// Returns the playfield index for coordinates, which wrap around the
+/// playfield if `WRAP` is set. Returns `None` if the coordinates are out of
+/// bounds.
+fn cell_index(x: i32, y: i32) -> Option<usize> {
+    let (x, y) = if WRAP {
+        (x.rem_euclid(WIDTH as i32), y.rem_euclid(HEIGHT as i32))
+    } else {
+        (x, y)
+    };
+    let (x, y) = (usize::try_from(x).ok()?, usize::try_from(y).ok()?);
+    (x < WIDTH && y < HEIGHT).then(|| x + y * WIDTH)
+}
+
+/// Returns whether a value is parsed as a command in command mode.
+#[allow(dead_code)]
+fn is_command(value: i32) -> bool {
+    char::from_u32(0u32.wrapping_add_signed(value))
+        .is_some_and(|c| "0123456789+-*/%!`><^v?_|\":\\$.,#gp&~@".contains(c))
+}
+
+/// Returns the position one cell in a direction from a position, wrapping
+/// around the edges of the playfield.
+#[allow(dead_code)]
+fn step(x: usize, y: usize, direction: Direction) -> (usize, usize) {
+    match direction {
+        Direction::Right => (if x + 1 < WIDTH { x + 1 } else { 0 }, y),
+        Direction::Down => (x, if y + 1 < HEIGHT { y + 1 } else { 0 }),
+        Direction::Left => (x.checked_sub(1).unwrap_or(WIDTH - 1), y),
+        Direction::Up => (x, y.checked_sub(1).unwrap_or(HEIGHT - 1)),
+    }
+}
+"#;
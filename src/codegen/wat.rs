@@ -0,0 +1,629 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+};
+
+use crate::{
+    common::Playfield,
+    ir::{
+        Block, Exit, Instruction, Label, Program, State,
+        ops::{BinOp, DivOp, UnOp},
+        state::{Direction, Mode},
+    },
+};
+
+/// The size of a WebAssembly memory page in bytes.
+const PAGE_SIZE: usize = 0x10000;
+
+/// The offset of the scratch buffer for formatting output. The runtime expects
+/// the scratch buffer to be 32 bytes long with a space at offset 12.
+const SCRATCH: usize = 0;
+
+/// The prompt strings used by the runtime when dividing by zero. The runtime
+/// expects them to start at offset 32 and have these lengths.
+const PROMPTS: [&str; 3] = ["What do you want ", "/0 to be? ", "%0 to be? "];
+
+/// Generates a WebAssembly text module from a program and its playfield.
+pub fn generate_program(program: &Program, playfield: &Playfield) -> String {
+    let mut code = String::new();
+    write_module(&mut code, program, playfield).expect("writing to a string should not fail");
+    code
+}
+
+/// Writes a WebAssembly text module.
+fn write_module(f: &mut impl Write, program: &Program, playfield: &Playfield) -> fmt::Result {
+    let layout = Layout::new(program, playfield);
+    let indices: BTreeMap<&Label, usize> = program
+        .blocks
+        .keys()
+        .enumerate()
+        .map(|(i, l)| (l, i))
+        .collect();
+
+    f.write_str(HEADER)?;
+    writeln!(f, "  (memory (export \"memory\") {})", layout.pages())?;
+    writeln!(
+        f,
+        "  (data (i32.const 0) \"{}\")",
+        escape_bytes(&layout.data)
+    )?;
+    writeln!(f)?;
+    writeln!(f, "  ;; The address of the playfield cells.")?;
+    writeln!(
+        f,
+        "  (global $playfield (export \"playfield\") i32 (i32.const {}))",
+        layout.playfield
+    )?;
+    writeln!(f, "  ;; The width of the playfield in cells.")?;
+    writeln!(
+        f,
+        "  (global $width (export \"width\") i32 (i32.const {}))",
+        layout.width
+    )?;
+    writeln!(f, "  ;; The height of the playfield in cells.")?;
+    writeln!(
+        f,
+        "  (global $height (export \"height\") i32 (i32.const {}))",
+        layout.height
+    )?;
+    writeln!(
+        f,
+        "  ;; Whether get and put coordinates wrap around the playfield."
+    )?;
+    writeln!(
+        f,
+        "  (global $wrap i32 (i32.const {}))",
+        u8::from(playfield.standard().wraps_coordinates())
+    )?;
+    writeln!(f, "  ;; The address of the bottom of the stack.")?;
+    writeln!(
+        f,
+        "  (global $stack_base (export \"stack_base\") i32 (i32.const {}))",
+        layout.stack_base
+    )?;
+    writeln!(f, "  ;; The address above the top value of the stack.")?;
+    writeln!(
+        f,
+        "  (global $sp (export \"stack_pointer\") (mut i32) (i32.const {}))",
+        layout.stack_base
+    )?;
+    f.write_str(RUNTIME)?;
+    writeln!(f)?;
+    writeln!(
+        f,
+        "  ;; Runs the program. Returns 0 if the program ended, or 1 if the host"
+    )?;
+    writeln!(
+        f,
+        "  ;; was asked to recompile the program after it modified itself."
+    )?;
+    writeln!(f, "  (func $run (export \"run\") (result i32)")?;
+    writeln!(f, "    (local $a i32) (local $b i32) (local $block i32)")?;
+    writeln!(
+        f,
+        "    (local.set $block (i32.const {}))",
+        indices[&Label::Main]
+    )?;
+    writeln!(f, "    loop $dispatch")?;
+
+    let count = program.blocks.len();
+    for index in (0..count).rev() {
+        writeln!(f, "{}block $block_{index}", indent(count - index))?;
+    }
+
+    let targets: Vec<String> = (0..count).map(|i| format!("$block_{i}")).collect();
+    writeln!(f, "{}local.get $block", indent(count + 1))?;
+    writeln!(
+        f,
+        "{}br_table {} $block_{}",
+        indent(count + 1),
+        targets.join(" "),
+        count - 1
+    )?;
+
+    let mut writer = BlockWriter {
+        f,
+        indices: &indices,
+        layout: &layout,
+        indent: String::new(),
+    };
+
+    for (index, (label, block)) in program.blocks.iter().enumerate() {
+        writer.indent = indent(count - index);
+        writeln!(writer.f, "{}end", writer.indent)?;
+        writer.write_block(label, block)?;
+    }
+
+    writeln!(f, "    end")?;
+    writeln!(f, "    unreachable)")?;
+    writeln!(f, ")")
+}
+
+/// Returns the indentation for a nesting depth inside the run function.
+fn indent(depth: usize) -> String {
+    " ".repeat(4 + depth * 2)
+}
+
+/// A writer for blocks in the run function.
+struct BlockWriter<'a, W: Write> {
+    /// The output.
+    f: &'a mut W,
+
+    /// The indices of labels.
+    indices: &'a BTreeMap<&'a Label, usize>,
+
+    /// The memory layout.
+    layout: &'a Layout<'a>,
+
+    /// The indentation of the current block.
+    indent: String,
+}
+
+impl<W: Write> BlockWriter<'_, W> {
+    /// Writes a block's code.
+    fn write_block(&mut self, label: &Label, block: &Block) -> fmt::Result {
+        self.line(&format!(";; {label}"))?;
+
+        for instruction in &block.instructions {
+            self.write_instruction(instruction)?;
+        }
+
+        match &block.exit {
+            Exit::Jump(l) => {
+                self.line(&format!(
+                    "(local.set $block (i32.const {}))",
+                    self.indices[l]
+                ))?;
+                self.line("(br $dispatch)")
+            }
+            Exit::Random(r, d, l, u) => {
+                let [r, d, l, u] = [r, d, l, u].map(|l| self.indices[l]);
+                self.line(&format!(
+                    "(local.set $block (call $choose (call $random) (i32.const {r}) (i32.const {d}) (i32.const {l}) (i32.const {u})))"
+                ))?;
+                self.line("(br $dispatch)")
+            }
+            Exit::Branch(t, e) => {
+                self.line(&format!(
+                    "(local.set $block (select (i32.const {}) (i32.const {}) (call $pop)))",
+                    self.indices[t], self.indices[e]
+                ))?;
+                self.line("(br $dispatch)")
+            }
+            Exit::End => self.line("(return (i32.const 0))"),
+        }
+    }
+
+    /// Writes an instruction.
+    fn write_instruction(&mut self, instruction: &Instruction) -> fmt::Result {
+        match instruction {
+            Instruction::Push(v) => {
+                self.line(&format!("(call $push (i32.const {}))", v.into_i32()))
+            }
+            Instruction::Unary(UnOp::Not) => self.line("(call $push (i32.eqz (call $pop)))"),
+            Instruction::Binary(o) => {
+                let op = match o {
+                    BinOp::Add => "i32.add",
+                    BinOp::Subtract => "i32.sub",
+                    BinOp::Multiply => "i32.mul",
+                    BinOp::Greater => "i32.gt_s",
+                    BinOp::Divide => "call $div",
+                    BinOp::Modulo => "i32.rem_s",
+                };
+                self.line("(local.set $b (call $pop))")?;
+                self.line("(local.set $a (call $pop))")?;
+                self.line(&format!(
+                    "(call $push ({op} (local.get $a) (local.get $b)))"
+                ))
+            }
+            Instruction::Divide(o) => {
+                let op = match o {
+                    DivOp::Quotient => 0,
+                    DivOp::Remainder => 1,
+                };
+                self.line(&format!("(call $divide (i32.const {op}))"))
+            }
+            Instruction::Duplicate => self.line("(call $push (call $peek))"),
+            Instruction::Swap => {
+                self.line("(local.set $a (call $pop))")?;
+                self.line("(local.set $b (call $pop))")?;
+                self.line("(call $push (local.get $a))")?;
+                self.line("(call $push (local.get $b))")
+            }
+            Instruction::Pop => self.line("(drop (call $pop))"),
+            Instruction::OutputInt => self.line("(call $output_int (call $pop))"),
+            Instruction::OutputChar => self.line("(call $output_char (call $pop))"),
+            Instruction::Get => self.line("(call $get)"),
+            Instruction::Put(s) => {
+                self.line("(if (call $put)")?;
+                self.line(&format!(
+                    "  (then (call $recompile {}) (return (i32.const 1))))",
+                    state_args(s)
+                ))
+            }
+            Instruction::InputInt => self.line("(call $push (call $input_int))"),
+            Instruction::InputChar => self.line("(call $push (call $input_char))"),
+            Instruction::Print(s) => {
+                let address = self.layout.strings[s.as_str()];
+                self.line(&format!(
+                    "(call $output (i32.const {address}) (i32.const {}))",
+                    s.len()
+                ))
+            }
+            Instruction::GetAt(x, y) => self.line(&format!(
+                "(call $push (i32.load (i32.const {})))",
+                self.layout.cell(*x, *y)
+            )),
+            Instruction::PutAt(x, y) => self.line(&format!(
+                "(i32.store (i32.const {}) (call $pop))",
+                self.layout.cell(*x, *y)
+            )),
+            Instruction::PutData => self.line("(drop (call $put))"),
+            Instruction::PutAtGuarded(x, y, m, s) => {
+                self.line(&format!(
+                    "(if (call $put_at_guarded (i32.const {}) (i32.const {}))",
+                    self.layout.cell(*x, *y),
+                    mode_code(*m)
+                ))?;
+                self.line(&format!(
+                    "  (then (call $recompile {}) (return (i32.const 1))))",
+                    state_args(s)
+                ))
+            }
+        }
+    }
+
+    /// Writes an indented line.
+    fn line(&mut self, line: &str) -> fmt::Result {
+        writeln!(self.f, "{}{line}", self.indent)
+    }
+}
+
+/// The layout of a module's linear memory.
+struct Layout<'a> {
+    /// The initial data from address 0 to the bottom of the stack.
+    data: Vec<u8>,
+
+    /// The addresses of strings.
+    strings: BTreeMap<&'a str, usize>,
+
+    /// The address of the playfield cells.
+    playfield: usize,
+
+    /// The playfield width in cells.
+    width: usize,
+
+    /// The playfield height in cells.
+    height: usize,
+
+    /// The address of the bottom of the stack.
+    stack_base: usize,
+}
+
+impl<'a> Layout<'a> {
+    /// Creates a new memory layout from a program and its playfield.
+    fn new(program: &'a Program, playfield: &Playfield) -> Self {
+        let mut data = vec![0; 32];
+        data[SCRATCH + 12] = b' ';
+        for prompt in PROMPTS {
+            data.extend_from_slice(prompt.as_bytes());
+        }
+
+        let mut strings = BTreeMap::new();
+        for instruction in program.blocks.values().flat_map(|b| &b.instructions) {
+            if let Instruction::Print(s) = instruction
+                && !strings.contains_key(s.as_str())
+            {
+                strings.insert(s.as_str(), data.len());
+                data.extend_from_slice(s.as_bytes());
+            }
+        }
+
+        data.resize(data.len().next_multiple_of(4), 0);
+        let (width, height) = playfield.bounds();
+        let playfield_address = data.len();
+        for y in 0..height {
+            for x in 0..width {
+                let value = playfield.get(x, y).unwrap_or_default().into_i32();
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        let stack_base = data.len();
+        Self {
+            data,
+            strings,
+            playfield: playfield_address,
+            width,
+            height,
+            stack_base,
+        }
+    }
+
+    /// Returns the initial number of memory pages, with at least one page for
+    /// the stack.
+    fn pages(&self) -> usize {
+        self.stack_base / PAGE_SIZE + 2
+    }
+
+    /// Returns the address of a playfield cell.
+    fn cell(&self, x: usize, y: usize) -> usize {
+        self.playfield + (x + y * self.width) * 4
+    }
+}
+
+/// Returns the WebAssembly arguments for recompiling at a state.
+fn state_args(state: &State) -> String {
+    let mode = mode_code(state.mode);
+    let direction = match state.direction {
+        Direction::Right => 0,
+        Direction::Down => 1,
+        Direction::Left => 2,
+        Direction::Up => 3,
+    };
+
+    format!(
+        "(i32.const {}) (i32.const {}) (i32.const {mode}) (i32.const {direction})",
+        state.x, state.y
+    )
+}
+
+/// Returns the integer code of a mode.
+fn mode_code(mode: Mode) -> i32 {
+    match mode {
+        Mode::Command => 0,
+        Mode::String => 1,
+    }
+}
+
+/// Returns bytes escaped for a WebAssembly text string.
+fn escape_bytes(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+    for byte in bytes {
+        match byte {
+            b'"' | b'\\' => escaped.push_str(&format!("\\{byte:02x}")),
+            b' '..=b'~' => escaped.push((*byte).into()),
+            _ => escaped.push_str(&format!("\\{byte:02x}")),
+        }
+    }
+    escaped
+}
+
+/// The start of a WebAssembly text module, including the imports.
+const HEADER: &str = r#";; Generated by Fungus from a Befunge program.
+;;
+;; The host must provide these imports:
+;; * `fungus.output(ptr: i32, len: i32)` - Outputs `len` bytes of UTF-8 from
+;;   the exported memory at `ptr`.
+;; * `fungus.input_char() -> i32` - Returns the next character of user input
+;;   as a Unicode scalar value, or -1 if there is no more input.
+;; * `fungus.input_int() -> i32` - Reads a line of user input and returns it
+;;   parsed as an integer, or -1 if it is not an integer.
+;; * `fungus.random() -> i32` - Returns a random direction in the lowest two
+;;   bits, where 0 is right, 1 is down, 2 is left, and 3 is up.
+;; * `fungus.recompile(x: i32, y: i32, mode: i32, direction: i32)` - Called
+;;   when the program modifies itself, before `run` returns 1. The program
+;;   should be recompiled from the playfield in memory and resumed at the
+;;   position `x`, `y` with the direction, in command mode if `mode` is 0 or
+;;   string mode if `mode` is 1. The stack is the memory from `stack_base` to
+;;   `stack_pointer`.
+(module
+  (import "fungus" "output" (func $output (param i32 i32)))
+  (import "fungus" "input_char" (func $input_char (result i32)))
+  (import "fungus" "input_int" (func $input_int (result i32)))
+  (import "fungus" "random" (func $random (result i32)))
+  (import "fungus" "recompile" (func $recompile (param i32 i32 i32 i32)))
+
+"#;
+
+/// The runtime support functions for a WebAssembly text module.
+const RUNTIME: &str = r#"
+  ;; Pushes a value to the stack, growing the memory if it is full.
+  (func $push (param $value i32)
+    (if (i32.gt_u (i32.add (global.get $sp) (i32.const 4))
+                  (i32.shl (memory.size) (i32.const 16)))
+      (then
+        (if (i32.eq (memory.grow (i32.const 1)) (i32.const -1))
+          (then unreachable))))
+    (i32.store (global.get $sp) (local.get $value))
+    (global.set $sp (i32.add (global.get $sp) (i32.const 4))))
+
+  ;; Pops a value from the stack, or returns 0 if the stack is empty.
+  (func $pop (result i32)
+    (if (result i32) (i32.eq (global.get $sp) (global.get $stack_base))
+      (then (i32.const 0))
+      (else
+        (global.set $sp (i32.sub (global.get $sp) (i32.const 4)))
+        (i32.load (global.get $sp)))))
+
+  ;; Returns the top value of the stack, or 0 if the stack is empty.
+  (func $peek (result i32)
+    (if (result i32) (i32.eq (global.get $sp) (global.get $stack_base))
+      (then (i32.const 0))
+      (else (i32.load (i32.sub (global.get $sp) (i32.const 4))))))
+
+  ;; Divides two values with wrapping for a non-zero right-hand operand.
+  (func $div (param $lhs i32) (param $rhs i32) (result i32)
+    (if (result i32) (i32.eq (local.get $rhs) (i32.const -1))
+      (then (i32.sub (i32.const 0) (local.get $lhs)))
+      (else (i32.div_s (local.get $lhs) (local.get $rhs)))))
+
+  ;; Pops two values and pushes their quotient if `op` is 0 or remainder if
+  ;; `op` is 1. The user is asked for the result if the right-hand operand is
+  ;; zero.
+  (func $divide (param $op i32)
+    (local $lhs i32) (local $rhs i32)
+    (local.set $rhs (call $pop))
+    (local.set $lhs (call $pop))
+    (if (local.get $rhs)
+      (then
+        (call $push
+          (if (result i32) (local.get $op)
+            (then (i32.rem_s (local.get $lhs) (local.get $rhs)))
+            (else (call $div (local.get $lhs) (local.get $rhs))))))
+      (else
+        (call $output (i32.const 32) (i32.const 17))
+        (call $output_decimal (local.get $lhs))
+        (call $output
+          (i32.add (i32.const 49) (i32.mul (local.get $op) (i32.const 10)))
+          (i32.const 10))
+        (call $push (call $input_int)))))
+
+  ;; Writes a value's decimal digits to the scratch buffer ending at offset 12
+  ;; and returns the offset of the first byte.
+  (func $write_decimal (param $value i32) (result i32)
+    (local $n i64) (local $ptr i32)
+    (local.set $n (i64.extend_i32_s (local.get $value)))
+    (if (i64.lt_s (local.get $n) (i64.const 0))
+      (then (local.set $n (i64.sub (i64.const 0) (local.get $n)))))
+    (local.set $ptr (i32.const 12))
+    (loop $digit
+      (local.set $ptr (i32.sub (local.get $ptr) (i32.const 1)))
+      (i32.store8 (local.get $ptr)
+        (i32.add (i32.const 48) (i32.wrap_i64 (i64.rem_u (local.get $n) (i64.const 10)))))
+      (local.set $n (i64.div_u (local.get $n) (i64.const 10)))
+      (br_if $digit (i64.ne (local.get $n) (i64.const 0))))
+    (if (i32.lt_s (local.get $value) (i32.const 0))
+      (then
+        (local.set $ptr (i32.sub (local.get $ptr) (i32.const 1)))
+        (i32.store8 (local.get $ptr) (i32.const 45))))
+    (local.get $ptr))
+
+  ;; Outputs a value as decimal digits.
+  (func $output_decimal (param $value i32)
+    (local $ptr i32)
+    (local.set $ptr (call $write_decimal (local.get $value)))
+    (call $output (local.get $ptr) (i32.sub (i32.const 12) (local.get $ptr))))
+
+  ;; Outputs a value as decimal digits followed by a space.
+  (func $output_int (param $value i32)
+    (local $ptr i32)
+    (local.set $ptr (call $write_decimal (local.get $value)))
+    (call $output (local.get $ptr) (i32.sub (i32.const 13) (local.get $ptr))))
+
+  ;; Outputs a value as a UTF-8 character. Values that are not Unicode scalar
+  ;; values are output as U+FFFD.
+  (func $output_char (param $c i32)
+    (if (i32.or (i32.gt_u (local.get $c) (i32.const 0x10ffff))
+                (i32.eq (i32.and (local.get $c) (i32.const 0xfffff800)) (i32.const 0xd800)))
+      (then (local.set $c (i32.const 0xfffd))))
+    (if (i32.lt_u (local.get $c) (i32.const 0x80))
+      (then
+        (i32.store8 (i32.const 16) (local.get $c))
+        (call $output (i32.const 16) (i32.const 1))
+        (return)))
+    (if (i32.lt_u (local.get $c) (i32.const 0x800))
+      (then
+        (i32.store8 (i32.const 16) (i32.or (i32.const 0xc0) (i32.shr_u (local.get $c) (i32.const 6))))
+        (i32.store8 (i32.const 17) (i32.or (i32.const 0x80) (i32.and (local.get $c) (i32.const 0x3f))))
+        (call $output (i32.const 16) (i32.const 2))
+        (return)))
+    (if (i32.lt_u (local.get $c) (i32.const 0x10000))
+      (then
+        (i32.store8 (i32.const 16) (i32.or (i32.const 0xe0) (i32.shr_u (local.get $c) (i32.const 12))))
+        (i32.store8 (i32.const 17)
+          (i32.or (i32.const 0x80) (i32.and (i32.shr_u (local.get $c) (i32.const 6)) (i32.const 0x3f))))
+        (i32.store8 (i32.const 18) (i32.or (i32.const 0x80) (i32.and (local.get $c) (i32.const 0x3f))))
+        (call $output (i32.const 16) (i32.const 3))
+        (return)))
+    (i32.store8 (i32.const 16) (i32.or (i32.const 0xf0) (i32.shr_u (local.get $c) (i32.const 18))))
+    (i32.store8 (i32.const 17)
+      (i32.or (i32.const 0x80) (i32.and (i32.shr_u (local.get $c) (i32.const 12)) (i32.const 0x3f))))
+    (i32.store8 (i32.const 18)
+      (i32.or (i32.const 0x80) (i32.and (i32.shr_u (local.get $c) (i32.const 6)) (i32.const 0x3f))))
+    (i32.store8 (i32.const 19) (i32.or (i32.const 0x80) (i32.and (local.get $c) (i32.const 0x3f))))
+    (call $output (i32.const 16) (i32.const 4)))
+
+  ;; Returns the address of an in-bounds playfield cell.
+  (func $cell (param $x i32) (param $y i32) (result i32)
+    (i32.add (global.get $playfield)
+      (i32.shl (i32.add (local.get $x) (i32.mul (local.get $y) (global.get $width)))
+               (i32.const 2))))
+
+  ;; Returns whether a position is inside the playfield.
+  (func $in_bounds (param $x i32) (param $y i32) (result i32)
+    (i32.and (i32.lt_u (local.get $x) (global.get $width))
+             (i32.lt_u (local.get $y) (global.get $height))))
+
+  ;; Wraps a coordinate around an axis with a size if $wrap is set.
+  (func $wrap (param $value i32) (param $size i32) (result i32)
+    (if (i32.eqz (global.get $wrap))
+      (then (return (local.get $value))))
+    (local.set $value (i32.rem_s (local.get $value) (local.get $size)))
+    (if (result i32) (i32.lt_s (local.get $value) (i32.const 0))
+      (then (i32.add (local.get $value) (local.get $size)))
+      (else (local.get $value))))
+
+  ;; Pops a position and pushes the playfield value at the position, or 0 if
+  ;; the position is out of bounds.
+  (func $get
+    (local $x i32) (local $y i32)
+    (local.set $y (call $wrap (call $pop) (global.get $height)))
+    (local.set $x (call $wrap (call $pop) (global.get $width)))
+    (call $push
+      (if (result i32) (call $in_bounds (local.get $x) (local.get $y))
+        (then (i32.load (call $cell (local.get $x) (local.get $y))))
+        (else (i32.const 0)))))
+
+  ;; Pops a position and a value and stores the value in the playfield at the
+  ;; position. Returns whether an in-bounds value was changed.
+  (func $put (result i32)
+    (local $x i32) (local $y i32) (local $value i32) (local $cell i32)
+    (local.set $y (call $wrap (call $pop) (global.get $height)))
+    (local.set $x (call $wrap (call $pop) (global.get $width)))
+    (local.set $value (call $pop))
+    (if (i32.eqz (call $in_bounds (local.get $x) (local.get $y)))
+      (then (return (i32.const 0))))
+    (local.set $cell (call $cell (local.get $x) (local.get $y)))
+    (if (i32.eq (i32.load (local.get $cell)) (local.get $value))
+      (then (return (i32.const 0))))
+    (i32.store (local.get $cell) (local.get $value))
+    (i32.const 1))
+
+  ;; Returns whether a value is parsed as a command in command mode. The
+  ;; commands are the set bits of two masks for the ASCII values below 64 and
+  ;; from 64 to 127.
+  (func $is_command (param $value i32) (result i32)
+    (if (result i32) (i32.lt_u (local.get $value) (i32.const 64))
+      (then
+        (i32.wrap_i64
+          (i64.and (i64.shr_u (i64.const 0xd7fffc7e00000000)
+                              (i64.extend_i32_u (local.get $value)))
+                   (i64.const 1))))
+      (else
+        (if (result i32) (i32.lt_u (local.get $value) (i32.const 128))
+          (then
+            (i32.wrap_i64
+              (i64.and (i64.shr_u (i64.const 0x50410081d0000001)
+                                  (i64.extend_i32_u (i32.sub (local.get $value) (i32.const 64))))
+                       (i64.const 1))))
+          (else (i32.const 0))))))
+
+  ;; Pops a value and stores it in an in-bounds playfield cell that is reached
+  ;; in command mode if `mode` is 0 or string mode if `mode` is 1. Returns
+  ;; whether the value changes how the cell is parsed in the mode.
+  (func $put_at_guarded (param $cell i32) (param $mode i32) (result i32)
+    (local $value i32) (local $previous_value i32)
+    (local.set $value (call $pop))
+    (local.set $previous_value (i32.load (local.get $cell)))
+    (i32.store (local.get $cell) (local.get $value))
+    (if (i32.eq (local.get $previous_value) (local.get $value))
+      (then (return (i32.const 0))))
+    (i32.or (local.get $mode)
+      (i32.or (call $is_command (local.get $previous_value))
+              (call $is_command (local.get $value)))))
+
+  ;; Returns one of four values for a random direction.
+  (func $choose (param $direction i32)
+    (param $right i32) (param $down i32) (param $left i32) (param $up i32) (result i32)
+    (block $up
+      (block $left
+        (block $down
+          (block $right
+            (br_table $right $down $left $up
+              (i32.and (local.get $direction) (i32.const 3))))
+          (return (local.get $right)))
+        (return (local.get $down)))
+      (return (local.get $left)))
+    (local.get $up))
+"#;
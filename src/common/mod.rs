@@ -1,5 +1,7 @@
 mod playfield;
+mod standard;
 mod value;
 
 pub use playfield::Playfield;
+pub use standard::Standard;
 pub use value::Value;
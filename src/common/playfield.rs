@@ -1,6 +1,6 @@
 use std::mem;
 
-use super::Value;
+use super::{Standard, Value};
 
 /// A Befunge playfield.
 pub struct Playfield {
@@ -12,21 +12,28 @@ pub struct Playfield {
 
     /// The cells.
     cells: Vec<Value>,
+
+    /// The standard that the playfield follows.
+    standard: Standard,
 }
 
 impl Playfield {
-    /// Creates a new playfield from source code.
-    pub fn new(source: &str) -> Self {
+    /// Creates a new playfield from source code that follows a standard. If
+    /// the standard has fixed bounds, then source code outside of them is
+    /// truncated.
+    pub fn new(source: &str, standard: Standard) -> Self {
         let mut lines = source.lines();
 
-        let width = lines.clone().fold(1, |a, l| l.chars().count().max(a));
-        let height = lines.clone().count().max(1);
+        let (width, height) = standard.fixed_bounds().unwrap_or_else(|| {
+            let width = lines.clone().fold(1, |a, l| l.chars().count().max(a));
+            (width, lines.clone().count().max(1))
+        });
         assert!(width > 0 && height > 0);
 
         let mut cells = vec![' '.into(); width * height];
         for row in cells.chunks_exact_mut(width) {
             if let Some(line) = lines.next() {
-                let line: Box<[Value]> = line.chars().map(Into::into).collect();
+                let line: Box<[Value]> = line.chars().take(width).map(Into::into).collect();
                 row[..line.len()].copy_from_slice(&line);
             }
         }
@@ -35,6 +42,7 @@ impl Playfield {
             width,
             height,
             cells,
+            standard,
         }
     }
 
@@ -43,6 +51,34 @@ impl Playfield {
         (self.width, self.height)
     }
 
+    /// Returns the standard that the playfield follows.
+    pub fn standard(&self) -> Standard {
+        self.standard
+    }
+
+    /// Returns the position in cells for the coordinates of a get or put
+    /// command. Coordinates wrap around the playfield if its standard wraps
+    /// them. Returns `None` if the coordinates are out of bounds.
+    pub fn position(&self, x: i32, y: i32) -> Option<(usize, usize)> {
+        let (x, y) = if self.standard.wraps_coordinates() {
+            (wrap(x, self.width), wrap(y, self.height))
+        } else {
+            (usize::try_from(x).ok()?, usize::try_from(y).ok()?)
+        };
+
+        (x < self.width && y < self.height).then_some((x, y))
+    }
+
+    /// Returns the cells in row-major order.
+    pub fn cells(&self) -> &[Value] {
+        &self.cells
+    }
+
+    /// Returns the cells in row-major order.
+    pub fn cells_mut(&mut self) -> &mut [Value] {
+        &mut self.cells
+    }
+
     /// Returns the value at a position in cells. Returns `None` if the position
     /// is out of bounds.
     pub fn get(&self, x: usize, y: usize) -> Option<Value> {
@@ -64,3 +100,10 @@ impl Playfield {
         }
     }
 }
+
+/// Wraps a coordinate around an axis with a size.
+fn wrap(coordinate: i32, size: usize) -> usize {
+    let size = i64::try_from(size).expect("playfield size should fit in an `i64`");
+    usize::try_from(i64::from(coordinate).rem_euclid(size))
+        .expect("wrapped coordinate should not be negative")
+}
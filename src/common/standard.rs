@@ -0,0 +1,34 @@
+/// A Befunge standard that defines the playfield's size and how gets and puts
+/// handle coordinates outside of it.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum Standard {
+    /// Befunge-93 with a playfield sized to fit the source code. Gets outside
+    /// of the playfield push 0 and puts outside of it are ignored.
+    #[default]
+    Fungus,
+
+    /// Strict Befunge-93 with a fixed 80x25 playfield. Source code outside of
+    /// the playfield is truncated, and get and put coordinates wrap around the
+    /// playfield.
+    Befunge93Strict,
+}
+
+impl Standard {
+    /// Returns the fixed bounds of the playfield in cells. Returns `None` if
+    /// the playfield is sized to fit the source code.
+    pub fn fixed_bounds(self) -> Option<(usize, usize)> {
+        match self {
+            Self::Fungus => None,
+            Self::Befunge93Strict => Some((80, 25)),
+        }
+    }
+
+    /// Returns whether get and put coordinates wrap around the playfield
+    /// instead of being out of bounds.
+    pub fn wraps_coordinates(self) -> bool {
+        match self {
+            Self::Fungus => false,
+            Self::Befunge93Strict => true,
+        }
+    }
+}
@@ -1,5 +1,6 @@
 /// A Befunge value.
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[repr(transparent)]
 pub struct Value {
     /// The inner value.
     value: i32,
@@ -1,6 +1,18 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    result,
+    time::Duration,
+};
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use fungus::{
+    Standard,
+    codegen::Target,
+    dump,
+    interpret::{Limits, ScriptedRandom},
+    ir::state::Direction,
+    optimize::{self, Level},
+};
 
 use crate::error::Result;
 
@@ -17,26 +29,331 @@ impl Config {
         Ok(Self { args })
     }
 
-    /// Returns the path to the source file.
-    pub fn path(&self) -> &Path {
-        &self.args.path
+    /// Returns the source code input.
+    pub fn source(&self) -> Source<'_> {
+        match &self.args.path {
+            Some(path) if path.as_os_str() == "-" => Source::Stdin,
+            Some(path) => Source::File(path),
+            None => Source::Eval(&self.args.eval),
+        }
     }
 
-    /// Returns whether to print the program as pseudo-assembly.
-    pub fn dump(&self) -> bool {
-        self.args.dump
+    /// Returns the format to print the program in. Returns `None` if the
+    /// program should be interpreted instead of printed.
+    pub fn dump_format(&self) -> Option<dump::Format> {
+        match self.args.dump_format {
+            Some(DumpFormat::Text) => Some(dump::Format::Text),
+            Some(DumpFormat::Json) => Some(dump::Format::Json),
+            Some(DumpFormat::Dot) => Some(dump::Format::Dot),
+            None if self.args.dump => Some(dump::Format::Text),
+            None => None,
+        }
     }
+
+    /// Returns the compilation stages to print the program after and the target
+    /// languages to print the program as.
+    pub fn emits(&self) -> &[Emit] {
+        &self.args.emit
+    }
+
+    /// Returns the standard to interpret the program with.
+    pub fn standard(&self) -> Standard {
+        match self.args.std {
+            Std::Fungus => Standard::Fungus,
+            Std::Befunge93Strict => Standard::Befunge93Strict,
+        }
+    }
+
+    /// Returns the options for optimizing the program.
+    pub fn options(&self) -> optimize::Options {
+        let level = match self.args.opt_level {
+            0 => Level::None,
+            1 => Level::Once,
+            _ => Level::Full,
+        };
+
+        let disabled_steps = self.args.no_opt.iter().map(|s| s.to_step()).collect();
+        optimize::Options {
+            level,
+            disabled_steps,
+        }
+    }
+
+    /// Returns whether to print statistics about optimizing the program.
+    pub fn stats(&self) -> bool {
+        self.args.stats
+    }
+
+    /// Returns whether to compile the program to machine code instead of
+    /// interpreting it.
+    #[cfg(feature = "jit")]
+    pub fn jit(&self) -> bool {
+        self.args.jit
+    }
+
+    /// Returns the seed for the random source. Returns `None` if the random
+    /// source should be seeded by the operating system.
+    pub fn seed(&self) -> Option<u64> {
+        self.args.seed
+    }
+
+    /// Returns the scripted random source. Returns `None` if the random
+    /// directions should not be scripted.
+    pub fn random_script(&self) -> Option<ScriptedRandom> {
+        self.args.random_script.clone()
+    }
+
+    /// Returns the directory to cache compiled programs in. Returns `None` if
+    /// compiled programs should not be cached.
+    pub fn cache_dir(&self) -> Option<&Path> {
+        self.args.cache.as_deref()
+    }
+
+    /// Returns the limits for interpreting the program.
+    pub fn limits(&self) -> Limits {
+        Limits {
+            max_steps: self.args.max_steps,
+            max_stack: self.args.max_stack,
+            max_time: self.args.max_time.map(Duration::from_millis),
+        }
+    }
+}
+
+/// A source code input.
+pub enum Source<'a> {
+    /// Source code from a file path.
+    File(&'a Path),
+
+    /// Source code from the standard input stream.
+    Stdin,
+
+    /// Source code from playfield rows given on the command line.
+    Eval(&'a [String]),
 }
 
 /// Command line arguments.
 #[derive(Parser)]
 #[command(bin_name("fungus"), version, about)]
 struct Args {
-    /// The path to the source file.
-    #[arg(help = "Source file path")]
-    path: PathBuf,
+    /// The path to the source file, or `-` for the standard input stream.
+    #[arg(
+        required_unless_present("eval"),
+        conflicts_with("eval"),
+        help = "Source file path, or '-' for stdin"
+    )]
+    path: Option<PathBuf>,
+
+    /// The playfield rows of an inline program.
+    #[arg(short, long, value_name = "CODE", help = "Inline playfield row")]
+    eval: Vec<String>,
 
     /// Whether to print the program as pseudo-assembly.
     #[arg(short, long, help = "Print pseudo-assembly")]
     dump: bool,
+
+    /// The format to print the program in.
+    #[arg(long, value_name = "FORMAT", help = "Print the program in a format")]
+    dump_format: Option<DumpFormat>,
+
+    /// The compilation stages to print the program after.
+    #[arg(
+        long,
+        value_name = "STAGE",
+        value_delimiter(','),
+        help = "Print the program after compilation stages or as source code"
+    )]
+    emit: Vec<Emit>,
+
+    /// The Befunge standard.
+    #[arg(
+        long,
+        value_name = "STD",
+        default_value = "fungus",
+        help = "Befunge standard to follow"
+    )]
+    std: Std,
+
+    /// The optimization level.
+    #[arg(
+        short = 'O',
+        value_name = "LEVEL",
+        default_value_t = 2,
+        value_parser = clap::value_parser!(u8).range(0..=2),
+        help = "Optimization level (0-2)"
+    )]
+    opt_level: u8,
+
+    /// The optimization steps that should not be run.
+    #[arg(
+        long,
+        value_name = "STEP",
+        value_delimiter(','),
+        help = "Disable an optimization step"
+    )]
+    no_opt: Vec<OptStep>,
+
+    /// Whether to print statistics about optimizing the program.
+    #[arg(long, help = "Print optimization statistics")]
+    stats: bool,
+
+    /// The directory to cache compiled programs in.
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Cache compiled programs in a directory"
+    )]
+    cache: Option<PathBuf>,
+
+    /// The seed for the random source.
+    #[arg(short, long, help = "Seed for random directions")]
+    seed: Option<u64>,
+
+    /// The repeated sequence of directions for the random source.
+    #[arg(
+        long,
+        value_name = "DIRECTIONS",
+        value_parser = parse_random_script,
+        conflicts_with = "seed",
+        help = "Repeated random directions (r, d, l, u)"
+    )]
+    random_script: Option<ScriptedRandom>,
+
+    /// Whether to compile the program to machine code instead of interpreting
+    /// it.
+    #[cfg(feature = "jit")]
+    #[arg(long, help = "Compile to machine code before running")]
+    jit: bool,
+
+    /// The maximum number of executed instructions and block exits.
+    #[arg(long, value_name = "STEPS", help = "Maximum executed steps")]
+    max_steps: Option<u64>,
+
+    /// The maximum number of values on the stack.
+    #[arg(long, value_name = "VALUES", help = "Maximum stack depth")]
+    max_stack: Option<usize>,
+
+    /// The maximum wall-clock running time in milliseconds.
+    #[arg(long, value_name = "MILLIS", help = "Maximum running time")]
+    max_time: Option<u64>,
+}
+
+/// A command line format for printing the program.
+#[derive(Clone, Copy, ValueEnum)]
+enum DumpFormat {
+    /// Human-readable pseudo-assembly.
+    Text,
+
+    /// Machine-readable JSON.
+    Json,
+
+    /// A Graphviz DOT digraph of the control flow graph.
+    Dot,
+}
+
+/// A command line Befunge standard.
+#[derive(Clone, Copy, ValueEnum)]
+enum Std {
+    /// Befunge-93 with a playfield sized to fit the source code.
+    Fungus,
+
+    /// Strict Befunge-93 with a fixed 80x25 playfield and wrapping get and put
+    /// coordinates.
+    #[value(name = "93-strict")]
+    Befunge93Strict,
+}
+
+/// A compilation stage to print the program after, or a target language to
+/// print the program as.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Emit {
+    /// The program after parsing.
+    Parse,
+
+    /// The program after every optimization step.
+    Steps,
+
+    /// The program after every optimization step that changed it.
+    ChangedSteps,
+
+    /// The optimized program as generated C source code.
+    C,
+
+    /// The optimized program as a generated Rust module.
+    Rust,
+
+    /// The optimized program as a generated WebAssembly text module.
+    Wat,
+
+    /// The optimized program as a generated Befunge-93 playfield.
+    Befunge,
+}
+
+impl Emit {
+    /// Returns the target language to generate source code in. Returns `None`
+    /// if the emit does not generate source code.
+    pub fn target(self) -> Option<Target> {
+        match self {
+            Self::Parse | Self::Steps | Self::ChangedSteps => None,
+            Self::C => Some(Target::C),
+            Self::Rust => Some(Target::Rust),
+            Self::Wat => Some(Target::Wat),
+            Self::Befunge => Some(Target::Befunge),
+        }
+    }
+}
+
+/// A command line optimization step.
+#[derive(Clone, Copy, ValueEnum)]
+enum OptStep {
+    /// Basic block merging.
+    MergeBlocks,
+
+    /// Jump threading.
+    ThreadJumps,
+
+    /// Dead code elimination.
+    RemoveUnreachableBlocks,
+
+    /// Peephole optimization.
+    ReplaceInstructions,
+
+    /// Jump to exit optimization.
+    ReplaceJumpsToExits,
+
+    /// Branch optimization.
+    OptimizeBranches,
+
+    /// Coordinate range analysis.
+    AnalyzeRanges,
+}
+
+impl OptStep {
+    /// Converts the command line optimization step to an optimization step.
+    fn to_step(self) -> optimize::Step {
+        match self {
+            Self::MergeBlocks => optimize::Step::MergeBlocks,
+            Self::ThreadJumps => optimize::Step::ThreadJumps,
+            Self::RemoveUnreachableBlocks => optimize::Step::RemoveUnreachableBlocks,
+            Self::ReplaceInstructions => optimize::Step::ReplaceInstructions,
+            Self::ReplaceJumpsToExits => optimize::Step::ReplaceJumpsToExits,
+            Self::OptimizeBranches => optimize::Step::OptimizeBranches,
+            Self::AnalyzeRanges => optimize::Step::AnalyzeRanges,
+        }
+    }
+}
+
+/// Parses a scripted random source from a sequence of direction letters.
+fn parse_random_script(script: &str) -> result::Result<ScriptedRandom, String> {
+    let directions = script
+        .chars()
+        .map(|c| match c {
+            'r' => Ok(Direction::Right),
+            'd' => Ok(Direction::Down),
+            'l' => Ok(Direction::Left),
+            'u' => Ok(Direction::Up),
+            _ => Err(format!("'{c}' is not a direction (r, d, l, u)")),
+        })
+        .collect::<result::Result<Vec<_>, _>>()?;
+
+    ScriptedRandom::new(directions).ok_or_else(|| "no directions were given".into())
 }
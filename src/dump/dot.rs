@@ -0,0 +1,97 @@
+use std::fmt::{self, Write};
+
+use crate::ir::{Block, Exit, Label, Program};
+
+/// Dumps a program to a Graphviz DOT string.
+pub fn dump_program(program: &Program) -> String {
+    let mut data = String::new();
+    write_program(&mut data, program).expect("writing to a string should not fail");
+    data
+}
+
+/// Writes a program as a Graphviz DOT digraph.
+fn write_program(f: &mut impl Write, program: &Program) -> fmt::Result {
+    writeln!(f, "digraph program {{")?;
+    writeln!(f, "    node [shape=box, fontname=monospace];")?;
+    writeln!(f, "    edge [fontname=monospace];")?;
+
+    for (label, block) in &program.blocks {
+        write_node(f, label, block)?;
+    }
+
+    for (label, block) in &program.blocks {
+        write_edges(f, label, &block.exit)?;
+    }
+
+    f.write_str("}")
+}
+
+/// Writes a block as a node.
+fn write_node(f: &mut impl Write, label: &Label, block: &Block) -> fmt::Result {
+    let mut text = format!("{label}:\n");
+    for instruction in &block.instructions {
+        writeln!(text, "{instruction}")?;
+    }
+
+    let attributes = if matches!(block.exit, Exit::End) {
+        writeln!(text, "{}", block.exit)?;
+        ", peripheries=2"
+    } else {
+        ""
+    };
+
+    writeln!(
+        f,
+        "    {} [label={}{attributes}];",
+        id(label),
+        text_label(&text)
+    )
+}
+
+/// Writes the edges from a block's exit.
+fn write_edges(f: &mut impl Write, label: &Label, exit: &Exit) -> fmt::Result {
+    match exit {
+        Exit::Jump(target) => write_edge(f, label, target, None),
+        Exit::Random(r, d, l, u) => {
+            write_edge(f, label, r, Some("right"))?;
+            write_edge(f, label, d, Some("down"))?;
+            write_edge(f, label, l, Some("left"))?;
+            write_edge(f, label, u, Some("up"))
+        }
+        Exit::Branch(t, e) => {
+            write_edge(f, label, t, Some("then"))?;
+            write_edge(f, label, e, Some("else"))
+        }
+        Exit::End => Ok(()),
+    }
+}
+
+/// Writes an edge between a source label and a target label with an optional
+/// edge label.
+fn write_edge(
+    f: &mut impl Write,
+    source: &Label,
+    target: &Label,
+    edge_label: Option<&str>,
+) -> fmt::Result {
+    write!(f, "    {} -> {}", id(source), id(target))?;
+    if let Some(edge_label) = edge_label {
+        write!(f, " [label={}]", quote(edge_label))?;
+    }
+    writeln!(f, ";")
+}
+
+/// Returns a quoted node ID for a label.
+fn id(label: &Label) -> String {
+    quote(&label.to_string())
+}
+
+/// Returns a quoted, left-justified multi-line node label from text.
+fn text_label(text: &str) -> String {
+    quote(text).replace('\n', "\\l")
+}
+
+/// Returns a quoted and escaped DOT string.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
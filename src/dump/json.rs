@@ -0,0 +1,211 @@
+use std::fmt::{self, Display, Formatter, Write};
+
+use crate::ir::{Block, Exit, Instruction, Label, Program, State};
+
+/// Dumps a program to a JSON string.
+pub fn dump_program(program: &Program) -> String {
+    let blocks = program.blocks.iter().map(|(l, b)| block(l, b)).collect();
+    Json::Object(vec![("blocks", Json::Array(blocks))]).to_string()
+}
+
+/// Converts a labeled block to JSON.
+fn block(label: &Label, block: &Block) -> Json {
+    let instructions = block.instructions.iter().map(instruction).collect();
+
+    Json::Object(vec![
+        ("label", self::label(label)),
+        ("instructions", Json::Array(instructions)),
+        ("exit", exit(&block.exit)),
+    ])
+}
+
+/// Converts an instruction to JSON.
+fn instruction(instruction: &Instruction) -> Json {
+    let (op, mut fields) = match instruction {
+        Instruction::Push(v) => ("push", vec![("value", v.into_i32().into())]),
+        Instruction::Unary(o) => ("unary", vec![("operator", o.to_string().into())]),
+        Instruction::Binary(o) => ("binary", vec![("operator", o.to_string().into())]),
+        Instruction::Divide(o) => ("divide", vec![("operator", o.to_string().into())]),
+        Instruction::Duplicate => ("duplicate", Vec::new()),
+        Instruction::Swap => ("swap", Vec::new()),
+        Instruction::Pop => ("pop", Vec::new()),
+        Instruction::OutputInt => ("output_int", Vec::new()),
+        Instruction::OutputChar => ("output_char", Vec::new()),
+        Instruction::Get => ("get", Vec::new()),
+        Instruction::Put(s) => ("put", vec![("state", state(s))]),
+        Instruction::InputInt => ("input_int", Vec::new()),
+        Instruction::InputChar => ("input_char", Vec::new()),
+        Instruction::Print(s) => ("print", vec![("string", s.as_str().into())]),
+        Instruction::GetAt(x, y) => ("get_at", vec![("x", (*x).into()), ("y", (*y).into())]),
+        Instruction::PutAt(x, y) => ("put_at", vec![("x", (*x).into()), ("y", (*y).into())]),
+        Instruction::PutData => ("put_data", Vec::new()),
+        Instruction::PutAtGuarded(x, y, m, s) => (
+            "put_at_guarded",
+            vec![
+                ("x", (*x).into()),
+                ("y", (*y).into()),
+                ("mode", m.to_string().into()),
+                ("state", state(s)),
+            ],
+        ),
+    };
+
+    fields.insert(0, ("op", op.into()));
+    Json::Object(fields)
+}
+
+/// Converts an exit to JSON.
+fn exit(exit: &Exit) -> Json {
+    let (kind, mut fields) = match exit {
+        Exit::Jump(l) => ("jump", vec![("target", label(l))]),
+        Exit::Random(r, d, l, u) => (
+            "random",
+            vec![
+                ("right", label(r)),
+                ("down", label(d)),
+                ("left", label(l)),
+                ("up", label(u)),
+            ],
+        ),
+        Exit::Branch(t, e) => ("branch", vec![("then", label(t)), ("else", label(e))]),
+        Exit::End => ("end", Vec::new()),
+    };
+
+    fields.insert(0, ("kind", kind.into()));
+    Json::Object(fields)
+}
+
+/// Converts a label to JSON.
+fn label(label: &Label) -> Json {
+    match label {
+        Label::Main => Json::Object(vec![("kind", "main".into())]),
+        Label::State(s) => {
+            let mut fields = state_fields(s);
+            fields.insert(0, ("kind", "state".into()));
+            Json::Object(fields)
+        }
+    }
+}
+
+/// Converts a state to JSON.
+fn state(state: &State) -> Json {
+    Json::Object(state_fields(state))
+}
+
+/// Converts a state to a vector of JSON object fields.
+fn state_fields(state: &State) -> Vec<(&'static str, Json)> {
+    vec![
+        ("x", state.x.into()),
+        ("y", state.y.into()),
+        ("mode", state.mode.to_string().into()),
+        ("direction", state.direction.to_string().into()),
+    ]
+}
+
+/// A JSON value.
+enum Json {
+    /// A number.
+    Number(i64),
+
+    /// A string.
+    String(String),
+
+    /// An array of values.
+    Array(Vec<Json>),
+
+    /// An object of named values.
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    /// Writes the value to a formatter with an indentation level.
+    fn write(&self, f: &mut Formatter<'_>, indent: usize) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "{n}"),
+            Self::String(s) => write_string(f, s),
+            Self::Array(values) if values.is_empty() => f.write_str("[]"),
+            Self::Array(values) => {
+                f.write_char('[')?;
+                for (index, value) in values.iter().enumerate() {
+                    write_separator(f, index, indent + 1)?;
+                    value.write(f, indent + 1)?;
+                }
+                write_line(f, indent)?;
+                f.write_char(']')
+            }
+            Self::Object(fields) if fields.is_empty() => f.write_str("{}"),
+            Self::Object(fields) => {
+                f.write_char('{')?;
+                for (index, (name, value)) in fields.iter().enumerate() {
+                    write_separator(f, index, indent + 1)?;
+                    write_string(f, name)?;
+                    f.write_str(": ")?;
+                    value.write(f, indent + 1)?;
+                }
+                write_line(f, indent)?;
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+impl From<i32> for Json {
+    fn from(value: i32) -> Self {
+        Self::Number(value.into())
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Self::Number(i64::try_from(value).expect("a coordinate should fit in an `i64`"))
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+/// Writes the separator before an element of an array or object.
+fn write_separator(f: &mut Formatter<'_>, index: usize, indent: usize) -> fmt::Result {
+    if index > 0 {
+        f.write_char(',')?;
+    }
+
+    write_line(f, indent)
+}
+
+/// Writes a line break followed by indentation.
+fn write_line(f: &mut Formatter<'_>, indent: usize) -> fmt::Result {
+    write!(f, "\n{:1$}", "", indent * 2)
+}
+
+/// Writes a quoted and escaped JSON string.
+fn write_string(f: &mut Formatter<'_>, value: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", u32::from(c))?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
@@ -0,0 +1,27 @@
+mod dot;
+mod json;
+
+use crate::ir::Program;
+
+/// A format for dumping a program.
+#[derive(Clone, Copy, Default)]
+pub enum Format {
+    /// Human-readable pseudo-assembly.
+    #[default]
+    Text,
+
+    /// Machine-readable JSON.
+    Json,
+
+    /// A Graphviz DOT digraph of the control flow graph.
+    Dot,
+}
+
+/// Dumps a program to a string in a format.
+pub fn dump_program(program: &Program, format: Format) -> String {
+    match format {
+        Format::Text => program.to_string(),
+        Format::Json => json::dump_program(program),
+        Format::Dot => dot::dump_program(program),
+    }
+}
@@ -6,6 +6,8 @@ use std::{
     result,
 };
 
+use fungus::{codegen, interpret};
+
 /// A result that may contain a Fungus error.
 pub type Result<T> = result::Result<T, Error>;
 
@@ -20,17 +22,45 @@ pub enum Error {
 
     /// An error caused by an I/O error while reading the source file.
     CouldNotReadSourceFile(io::Error),
+
+    /// An error caused by an I/O error while reading source code from the
+    /// standard input stream.
+    CouldNotReadSourceStdin(io::Error),
+
+    /// An error caused by an I/O error while writing output.
+    CouldNotWriteOutput(io::Error),
+
+    /// An error raised while generating source code.
+    Codegen(codegen::Error),
+
+    /// An error raised while interpreting the program.
+    Runtime(interpret::Error),
 }
 
 impl Error {
     /// Prints the error and returns an exit code.
     pub fn report(&self) -> ExitCode {
-        if let Self::Clap(e) = self {
-            let _ = e.print();
-            u8::try_from(e.exit_code()).unwrap_or(1).into()
-        } else {
-            let _ = writeln!(io::stderr(), "error: {self}");
-            ExitCode::FAILURE
+        match self {
+            Self::Clap(e) => {
+                let _ = e.print();
+                u8::try_from(e.exit_code()).unwrap_or(1).into()
+            }
+            // The output was closed by the reader (e.g. `fungus prog.bf | head`),
+            // so nobody is left to see any more output.
+            Self::CouldNotWriteOutput(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                ExitCode::SUCCESS
+            }
+            Self::Runtime(e) if e.is_broken_pipe() => ExitCode::SUCCESS,
+            _ => {
+                let _ = writeln!(io::stderr(), "error: {self}");
+                match self {
+                    Self::Runtime(interpret::Error::LimitExceeded(_, _)) => ExitCode::from(3),
+                    Self::CouldNotWriteOutput(_) | Self::Runtime(interpret::Error::Io(_)) => {
+                        ExitCode::from(4)
+                    }
+                    _ => ExitCode::FAILURE,
+                }
+            }
         }
     }
 }
@@ -41,12 +71,28 @@ impl From<clap::Error> for Error {
     }
 }
 
+impl From<codegen::Error> for Error {
+    fn from(value: codegen::Error) -> Self {
+        Self::Codegen(value)
+    }
+}
+
+impl From<interpret::Error> for Error {
+    fn from(value: interpret::Error) -> Self {
+        Self::Runtime(value)
+    }
+}
+
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
             Self::Clap(e) => Some(e),
             Self::SourceFileDoesNotExist => None,
-            Self::CouldNotReadSourceFile(e) => Some(e),
+            Self::CouldNotReadSourceFile(e)
+            | Self::CouldNotReadSourceStdin(e)
+            | Self::CouldNotWriteOutput(e) => Some(e),
+            Self::Codegen(e) => Some(e),
+            Self::Runtime(e) => Some(e),
         }
     }
 }
@@ -57,6 +103,10 @@ impl Display for Error {
             Self::Clap(e) => e.fmt(f),
             Self::SourceFileDoesNotExist => f.write_str("source file does not exist"),
             Self::CouldNotReadSourceFile(e) => write!(f, "could not read source file: {e}"),
+            Self::CouldNotReadSourceStdin(e) => write!(f, "could not read source from stdin: {e}"),
+            Self::CouldNotWriteOutput(e) => write!(f, "could not write output: {e}"),
+            Self::Codegen(e) => write!(f, "could not generate source code: {e}"),
+            Self::Runtime(e) => e.fmt(f),
         }
     }
 }
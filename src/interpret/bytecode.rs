@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use crate::ir::{Exit, Instruction, Label, Program, State, state::Mode};
+
+/// A program lowered to a flat array of operations. Each block is lowered to
+/// its instructions followed by a single exit operation, and exits refer to
+/// blocks by operation offsets instead of labels. Playfield cells at known
+/// positions are lowered to static variable slots.
+pub struct Bytecode {
+    /// The operations.
+    ops: Vec<Op>,
+
+    /// The positions of the cells of static variables, in slot order.
+    variables: Vec<(usize, usize)>,
+
+    /// The offsets of the first operations of blocks, in ascending order.
+    block_offsets: Vec<usize>,
+
+    /// The labels of blocks, in the same order as the block offsets.
+    labels: Vec<Label>,
+
+    /// The offset of the main entry point block.
+    entry: usize,
+}
+
+impl Bytecode {
+    /// Lowers a program to bytecode.
+    pub fn new(program: &Program) -> Self {
+        // Blocks are visited in label order, so the offset of a label's block
+        // can be found by binary searching the labels.
+        let labels: Vec<_> = program.blocks.keys().cloned().collect();
+        let mut block_offsets = Vec::with_capacity(program.blocks.len());
+        let mut offset = 0;
+        for block in program.blocks.values() {
+            block_offsets.push(offset);
+            offset += block.instructions.len() + 1;
+        }
+
+        let offset_of = |label| {
+            let index = labels
+                .binary_search(label)
+                .expect("exit label should have a block");
+            block_offsets[index]
+        };
+
+        let mut slots = BTreeMap::new();
+        let mut variables = Vec::new();
+        let mut slot = |x, y| {
+            *slots.entry((x, y)).or_insert_with(|| {
+                variables.push((x, y));
+                variables.len() - 1
+            })
+        };
+
+        let mut ops = Vec::with_capacity(offset);
+        for block in program.blocks.values() {
+            ops.extend(block.instructions.iter().map(|i| match i {
+                Instruction::GetAt(x, y) => Op::Load(slot(*x, *y)),
+                Instruction::PutAt(x, y) => Op::Store(slot(*x, *y)),
+                Instruction::PutAtGuarded(x, y, m, s) => {
+                    Op::GuardedStore(slot(*x, *y), *m, s.clone())
+                }
+                _ => Op::Instruction(i.clone()),
+            }));
+
+            ops.push(match &block.exit {
+                Exit::Jump(l) => Op::Jump(offset_of(l)),
+                Exit::Random(r, d, l, u) => {
+                    Op::Random(offset_of(r), offset_of(d), offset_of(l), offset_of(u))
+                }
+                Exit::Branch(t, e) => Op::Branch(offset_of(t), offset_of(e)),
+                Exit::End => Op::End,
+            });
+        }
+
+        let entry = offset_of(&Label::Main);
+        Self {
+            ops,
+            variables,
+            block_offsets,
+            labels,
+            entry,
+        }
+    }
+
+    /// Returns the operation at an offset.
+    pub fn op(&self, offset: usize) -> &Op {
+        &self.ops[offset]
+    }
+
+    /// Returns the positions of the cells of static variables, in slot order.
+    pub fn variables(&self) -> &[(usize, usize)] {
+        &self.variables
+    }
+
+    /// Returns the offset of the main entry point block.
+    pub fn entry(&self) -> usize {
+        self.entry
+    }
+
+    /// Returns the state of the block containing an offset. Returns `None` if
+    /// the block is the main entry point block.
+    pub fn state_at(&self, offset: usize) -> Option<&State> {
+        let index = self.block_offsets.partition_point(|o| *o <= offset) - 1;
+        match &self.labels[index] {
+            Label::Main => None,
+            Label::State(s) => Some(s),
+        }
+    }
+}
+
+/// An operation in bytecode.
+pub enum Op {
+    /// An operation to execute an instruction and continue to the next
+    /// operation.
+    Instruction(Instruction),
+
+    /// An operation to push the value of a static variable slot to the stack
+    /// and continue to the next operation.
+    Load(usize),
+
+    /// An operation to pop a value from the stack to a static variable slot
+    /// and continue to the next operation.
+    Store(usize),
+
+    /// An operation to pop a value from the stack to a static variable slot
+    /// whose cell is reached in a mode, and continue to the next operation. If
+    /// the value changes how the cell is parsed in the mode, then the program
+    /// is recompiled at a state.
+    GuardedStore(usize, Mode, State),
+
+    /// An unconditional jump to an offset.
+    Jump(usize),
+
+    /// A random branch to one of four offsets.
+    Random(usize, usize, usize, usize),
+
+    /// A conditional branch to one of two offsets.
+    Branch(usize, usize),
+
+    /// A program ending.
+    End,
+}
@@ -0,0 +1,53 @@
+use std::{
+    error,
+    fmt::{self, Display, Formatter},
+    io, result,
+};
+
+use crate::ir::State;
+
+use super::Limit;
+
+/// A result that may contain a runtime error.
+pub type Result<T> = result::Result<T, Error>;
+
+/// An error raised while interpreting a program.
+#[derive(Debug)]
+pub enum Error {
+    /// An error caused by a limit being exceeded in a block built at a state.
+    LimitExceeded(Limit, State),
+
+    /// An error caused by an I/O error from the I/O backend.
+    Io(io::Error),
+}
+
+impl Error {
+    /// Returns whether the error was caused by the output being closed.
+    pub fn is_broken_pipe(&self) -> bool {
+        matches!(self, Self::Io(e) if e.kind() == io::ErrorKind::BrokenPipe)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::LimitExceeded(_, _) => None,
+            Self::Io(e) => Some(e),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LimitExceeded(l, s) => write!(f, "{l} exceeded at {s}"),
+            Self::Io(e) => write!(f, "could not perform program I/O: {e}"),
+        }
+    }
+}
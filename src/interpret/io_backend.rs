@@ -0,0 +1,142 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+};
+
+/// An input and output backend for an interpreter.
+pub trait Io {
+    /// Reads a character of user input. Returns `None` if there is no more
+    /// input.
+    fn input_char(&mut self) -> io::Result<Option<char>>;
+
+    /// Reads an integer from a line of user input. Returns `None` if no integer
+    /// could be read.
+    fn input_int(&mut self) -> io::Result<Option<i32>>;
+
+    /// Outputs a string.
+    fn output_str(&mut self, value: &str) -> io::Result<()>;
+
+    /// Outputs a character.
+    fn output_char(&mut self, value: char) -> io::Result<()> {
+        self.output_str(value.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Outputs an integer followed by a space.
+    fn output_int(&mut self, value: i32) -> io::Result<()> {
+        self.output_str(&format!("{value} "))
+    }
+
+    /// Flushes any buffered output.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// An I/O backend using the standard input and output streams.
+#[derive(Default)]
+pub struct StdIo {
+    /// The character input buffer.
+    input_chars: VecDeque<char>,
+
+    /// Whether the standard input stream should not be read.
+    is_input_closed: bool,
+}
+
+impl Io for StdIo {
+    fn input_char(&mut self) -> io::Result<Option<char>> {
+        if self.input_chars.is_empty() && !self.is_input_closed {
+            let line = self.read_line()?;
+            self.input_chars.extend(line.chars());
+        }
+
+        Ok(self.input_chars.pop_front())
+    }
+
+    fn input_int(&mut self) -> io::Result<Option<i32>> {
+        if self.is_input_closed {
+            return Ok(None);
+        }
+
+        Ok(self.read_line()?.trim().parse().ok())
+    }
+
+    fn output_str(&mut self, value: &str) -> io::Result<()> {
+        io::stdout().write_all(value.as_bytes())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+impl StdIo {
+    /// Creates a new standard I/O backend that does not read the standard
+    /// input stream and always reports that there is no more input.
+    pub fn without_input() -> Self {
+        Self {
+            is_input_closed: true,
+            ..Self::default()
+        }
+    }
+
+    /// Reads a line of user input.
+    fn read_line(&mut self) -> io::Result<String> {
+        self.flush()?;
+
+        let mut line = String::new();
+        io::stdin().read_line(&mut line)?;
+        Ok(line)
+    }
+}
+
+/// An I/O backend using an in-memory input buffer and output buffer.
+#[derive(Default)]
+pub struct BufferIo {
+    /// The remaining input.
+    input: VecDeque<char>,
+
+    /// The output.
+    output: String,
+}
+
+impl BufferIo {
+    /// Creates a new buffer I/O backend from input.
+    pub fn new(input: &str) -> Self {
+        Self {
+            input: input.chars().collect(),
+            output: String::new(),
+        }
+    }
+
+    /// Returns the output.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// Converts the buffer I/O backend to its output.
+    pub fn into_output(self) -> String {
+        self.output
+    }
+}
+
+impl Io for BufferIo {
+    fn input_char(&mut self) -> io::Result<Option<char>> {
+        Ok(self.input.pop_front())
+    }
+
+    fn input_int(&mut self) -> io::Result<Option<i32>> {
+        let length = self
+            .input
+            .iter()
+            .position(|c| *c == '\n')
+            .map_or(self.input.len(), |i| i + 1);
+
+        let line: String = self.input.drain(..length).collect();
+        Ok(line.trim().parse().ok())
+    }
+
+    fn output_str(&mut self, value: &str) -> io::Result<()> {
+        self.output.push_str(value);
+        Ok(())
+    }
+}
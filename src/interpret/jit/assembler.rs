@@ -0,0 +1,279 @@
+//! A minimal x86-64 assembler for compiled blocks.
+//!
+//! Compiled blocks use a fixed register assignment:
+//! * `rbx` - The pointer to the compiled code's context.
+//! * `r12` - The pointer to the bottom of the stack.
+//! * `r13` - The length of the stack in values.
+//! * `eax` and `ecx` - Scratch registers for values.
+
+use super::context::{CELLS, MAX_STACK, REMAINING_STEPS, STACK, STACK_LEN};
+
+/// A general-purpose register for a value.
+#[derive(Clone, Copy)]
+pub enum Reg {
+    /// The `eax` register.
+    Eax,
+
+    /// The `ecx` register.
+    Ecx,
+}
+
+impl Reg {
+    /// Returns the register's number for ModRM encoding.
+    fn number(self) -> u8 {
+        match self {
+            Self::Eax => 0,
+            Self::Ecx => 1,
+        }
+    }
+}
+
+/// A condition for a conditional jump.
+#[derive(Clone, Copy)]
+pub enum Condition {
+    /// A condition where the last result was not zero.
+    NotZero,
+
+    /// A condition where the last unsigned subtraction borrowed.
+    Below,
+
+    /// A condition where the last unsigned comparison was greater.
+    Above,
+}
+
+/// A label for a position in machine code.
+#[derive(Clone, Copy)]
+pub struct Label(usize);
+
+/// An assembler that emits machine code to a buffer.
+#[derive(Default)]
+pub struct Assembler {
+    /// The machine code.
+    code: Vec<u8>,
+
+    /// The positions of bound labels.
+    labels: Vec<Option<usize>>,
+
+    /// The positions of 32-bit relative jump displacements and their labels.
+    fixups: Vec<(usize, Label)>,
+}
+
+impl Assembler {
+    /// Returns the current position in the machine code.
+    pub fn position(&self) -> usize {
+        self.code.len()
+    }
+
+    /// Creates a new unbound label.
+    pub fn new_label(&mut self) -> Label {
+        self.labels.push(None);
+        Label(self.labels.len() - 1)
+    }
+
+    /// Binds a label to the current position.
+    pub fn bind(&mut self, label: Label) {
+        self.labels[label.0] = Some(self.position());
+    }
+
+    /// Resolves jumps to labels and returns the machine code.
+    pub fn finish(mut self) -> Vec<u8> {
+        for (position, label) in self.fixups {
+            let target = self.labels[label.0].expect("jumped label should be bound");
+            let displacement = i32::try_from(target as i64 - (position as i64 + 4))
+                .expect("jump displacement should fit in 32 bits");
+            self.code[position..position + 4].copy_from_slice(&displacement.to_le_bytes());
+        }
+
+        self.code
+    }
+
+    /// Emits the function prologue, saving registers and loading the context
+    /// and stack from the first argument.
+    pub fn prologue(&mut self) {
+        self.emit(&[0x53]); // push rbx
+        self.emit(&[0x41, 0x54]); // push r12
+        self.emit(&[0x41, 0x55]); // push r13
+        self.emit(&[0x48, 0x89, 0xfb]); // mov rbx, rdi
+        self.load_stack();
+    }
+
+    /// Emits the function epilogue, storing the stack length and restoring
+    /// registers. The return value must already be in `rax`.
+    pub fn epilogue(&mut self) {
+        self.emit(&[0x4c, 0x89, 0x6b, STACK_LEN]); // mov [rbx + len], r13
+        self.emit(&[0x41, 0x5d]); // pop r13
+        self.emit(&[0x41, 0x5c]); // pop r12
+        self.emit(&[0x5b]); // pop rbx
+        self.emit(&[0xc3]); // ret
+    }
+
+    /// Emits code to pop a value from the stack to a register. The register is
+    /// set to zero if the stack is empty.
+    pub fn pop(&mut self, reg: Reg) {
+        let r = reg.number();
+        self.emit(&[0x4d, 0x85, 0xed]); // test r13, r13
+        self.emit(&[0x74, 0x09]); // jz .empty
+        self.emit(&[0x49, 0xff, 0xcd]); // dec r13
+        self.emit(&[0x43, 0x8b, 0x04 | r << 3, 0xac]); // mov reg, [r12 + r13 * 4]
+        self.emit(&[0xeb, 0x02]); // jmp .done
+        self.emit(&[0x31, 0xc0 | r << 3 | r]); // .empty: xor reg, reg
+    }
+
+    /// Emits code to copy the top value of the stack to `eax`. The register is
+    /// set to zero if the stack is empty.
+    pub fn peek(&mut self) {
+        self.emit(&[0x4d, 0x85, 0xed]); // test r13, r13
+        self.emit(&[0x74, 0x07]); // jz .empty
+        self.emit(&[0x43, 0x8b, 0x44, 0xac, 0xfc]); // mov eax, [r12 + r13 * 4 - 4]
+        self.emit(&[0xeb, 0x02]); // jmp .done
+        self.emit(&[0x31, 0xc0]); // .empty: xor eax, eax
+    }
+
+    /// Emits code to discard the top value of the stack if it is not empty.
+    pub fn drop_top(&mut self) {
+        self.emit(&[0x4d, 0x85, 0xed]); // test r13, r13
+        self.emit(&[0x74, 0x03]); // jz .done
+        self.emit(&[0x49, 0xff, 0xcd]); // dec r13
+    }
+
+    /// Emits code to push a register to the stack.
+    pub fn push(&mut self, reg: Reg) {
+        self.emit(&[0x43, 0x89, 0x04 | reg.number() << 3, 0xac]); // mov [r12 + r13 * 4], reg
+        self.emit(&[0x49, 0xff, 0xc5]); // inc r13
+    }
+
+    /// Emits code to push an immediate value to the stack.
+    pub fn push_imm(&mut self, value: i32) {
+        self.emit(&[0x43, 0xc7, 0x04, 0xac]); // mov dword [r12 + r13 * 4], value
+        self.emit(&value.to_le_bytes());
+        self.emit(&[0x49, 0xff, 0xc5]); // inc r13
+    }
+
+    /// Emits `add eax, ecx`.
+    pub fn add(&mut self) {
+        self.emit(&[0x01, 0xc8]);
+    }
+
+    /// Emits `sub eax, ecx`.
+    pub fn sub(&mut self) {
+        self.emit(&[0x29, 0xc8]);
+    }
+
+    /// Emits `imul eax, ecx`.
+    pub fn imul(&mut self) {
+        self.emit(&[0x0f, 0xaf, 0xc1]);
+    }
+
+    /// Emits code to set `eax` to 1 if `eax` is greater than `ecx` as a signed
+    /// value, or 0 otherwise.
+    pub fn greater(&mut self) {
+        self.emit(&[0x39, 0xc8]); // cmp eax, ecx
+        self.emit(&[0x0f, 0x9f, 0xc0]); // setg al
+        self.emit(&[0x0f, 0xb6, 0xc0]); // movzx eax, al
+    }
+
+    /// Emits code to set `eax` to 1 if `eax` is zero, or 0 otherwise.
+    pub fn not(&mut self) {
+        self.emit(&[0x85, 0xc0]); // test eax, eax
+        self.emit(&[0x0f, 0x94, 0xc0]); // sete al
+        self.emit(&[0x0f, 0xb6, 0xc0]); // movzx eax, al
+    }
+
+    /// Emits code to load a playfield cell to `eax` from a byte offset into the
+    /// cells.
+    pub fn load_cell(&mut self, offset: i32) {
+        self.emit(&[0x48, 0x8b, 0x43, CELLS]); // mov rax, [rbx + cells]
+        self.emit(&[0x8b, 0x80]); // mov eax, [rax + offset]
+        self.emit(&offset.to_le_bytes());
+    }
+
+    /// Emits code to store `eax` to a playfield cell at a byte offset into the
+    /// cells.
+    pub fn store_cell(&mut self, offset: i32) {
+        self.emit(&[0x48, 0x8b, 0x53, CELLS]); // mov rdx, [rbx + cells]
+        self.emit(&[0x89, 0x82]); // mov [rdx + offset], eax
+        self.emit(&offset.to_le_bytes());
+    }
+
+    /// Emits code to call a helper function with the context and an argument.
+    /// The stack is stored before the call and reloaded after it, and the
+    /// helper's return value is left in `rax`.
+    pub fn call(&mut self, address: usize, argument: u32) {
+        self.emit(&[0x4c, 0x89, 0x6b, STACK_LEN]); // mov [rbx + len], r13
+        self.emit(&[0x48, 0x89, 0xdf]); // mov rdi, rbx
+        self.emit(&[0xbe]); // mov esi, argument
+        self.emit(&argument.to_le_bytes());
+        self.emit(&[0x48, 0xb8]); // mov rax, address
+        self.emit(&(address as u64).to_le_bytes());
+        self.emit(&[0xff, 0xd0]); // call rax
+        self.load_stack();
+    }
+
+    /// Emits code to subtract 1 from the number of remaining steps. The
+    /// subtraction borrows if there were no remaining steps.
+    pub fn count_step(&mut self) {
+        self.emit(&[0x48, 0x83, 0x6b, REMAINING_STEPS, 0x01]); // sub qword [rbx + steps], 1
+    }
+
+    /// Emits code to compare the stack length with the maximum stack length.
+    pub fn compare_stack_limit(&mut self) {
+        self.emit(&[0x4c, 0x3b, 0x6b, MAX_STACK]); // cmp r13, [rbx + max_stack]
+    }
+
+    /// Emits `test rax, rax`.
+    pub fn test_rax(&mut self) {
+        self.emit(&[0x48, 0x85, 0xc0]);
+    }
+
+    /// Emits `test eax, eax`.
+    pub fn test_eax(&mut self) {
+        self.emit(&[0x85, 0xc0]);
+    }
+
+    /// Emits code to move an immediate value to a register. The upper 32 bits
+    /// of the 64-bit register are cleared.
+    pub fn mov_imm(&mut self, reg: Reg, value: u32) {
+        self.emit(&[0xb8 | reg.number()]);
+        self.emit(&value.to_le_bytes());
+    }
+
+    /// Emits `cmovnz eax, ecx`.
+    pub fn cmovnz(&mut self) {
+        self.emit(&[0x0f, 0x45, 0xc1]);
+    }
+
+    /// Emits an unconditional jump to a label.
+    pub fn jump(&mut self, label: Label) {
+        self.emit(&[0xe9]);
+        self.emit_fixup(label);
+    }
+
+    /// Emits a conditional jump to a label.
+    pub fn jump_if(&mut self, condition: Condition, label: Label) {
+        let opcode = match condition {
+            Condition::NotZero => 0x85,
+            Condition::Below => 0x82,
+            Condition::Above => 0x87,
+        };
+
+        self.emit(&[0x0f, opcode]);
+        self.emit_fixup(label);
+    }
+
+    /// Emits code to load the stack pointer and length from the context.
+    fn load_stack(&mut self) {
+        self.emit(&[0x4c, 0x8b, 0x63, STACK]); // mov r12, [rbx + stack]
+        self.emit(&[0x4c, 0x8b, 0x6b, STACK_LEN]); // mov r13, [rbx + len]
+    }
+
+    /// Emits a placeholder 32-bit displacement to a label.
+    fn emit_fixup(&mut self, label: Label) {
+        self.fixups.push((self.position(), label));
+        self.emit(&[0; 4]);
+    }
+
+    /// Emits bytes.
+    fn emit(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+}
@@ -0,0 +1,357 @@
+use std::{collections::BTreeMap, io};
+
+use crate::{
+    interpret::{Io, Limits, RandomSource},
+    ir::{
+        Block, Exit, Instruction, Label, Program, State,
+        ops::{BinOp, UnOp},
+    },
+};
+
+use super::{
+    assembler::{self, Assembler, Condition, Reg},
+    context::{self, Context, LIMIT_STACK, LIMIT_STEPS, STATUS_END, STATUS_FIRST_BLOCK},
+    memory::ExecutableMemory,
+};
+
+/// A program compiled to machine code.
+pub struct Code {
+    /// The executable memory containing the machine code.
+    memory: ExecutableMemory,
+
+    /// The compiled blocks, in label order.
+    blocks: Vec<CompiledBlock>,
+
+    /// The index of the main entry point block.
+    main: usize,
+}
+
+impl Code {
+    /// Compiles a program for playfield bounds and limits. Instructions that
+    /// are not compiled inline are added to tables.
+    pub fn new<I: Io, R: RandomSource>(
+        program: &Program,
+        bounds: (usize, usize),
+        limits: Limits,
+        tables: &mut Tables,
+    ) -> io::Result<Self> {
+        let statuses: BTreeMap<&Label, u64> =
+            program.blocks.keys().zip(STATUS_FIRST_BLOCK..).collect();
+
+        let mut compiler = Compiler {
+            assembler: Assembler::default(),
+            statuses,
+            bounds,
+            limits,
+            tables,
+            helpers: Helpers::new::<I, R>(),
+        };
+
+        let blocks = program
+            .blocks
+            .iter()
+            .map(|(label, block)| CompiledBlock {
+                offset: compiler.compile_block(block),
+                growth: block.instructions.iter().map(max_growth).sum(),
+                state: match label {
+                    Label::Main => None,
+                    Label::State(s) => Some(s.clone()),
+                },
+            })
+            .collect();
+
+        let main = program
+            .blocks
+            .keys()
+            .position(|l| *l == Label::Main)
+            .expect("program should have a main block");
+
+        Ok(Self {
+            memory: ExecutableMemory::new(&compiler.assembler.finish())?,
+            blocks,
+            main,
+        })
+    }
+
+    /// Returns the index of the main entry point block.
+    pub fn main(&self) -> usize {
+        self.main
+    }
+
+    /// Returns the maximum number of values a block may add to the stack.
+    pub fn growth(&self, block: usize) -> usize {
+        self.blocks[block].growth
+    }
+
+    /// Returns the state of a block. Returns `None` if the block is the main
+    /// entry point block.
+    pub fn state(&self, block: usize) -> Option<&State> {
+        self.blocks[block].state.as_ref()
+    }
+
+    /// Runs a block with a context and returns its status.
+    ///
+    /// # Safety
+    /// The code must have been compiled with the same context type, the
+    /// context pointer must be valid, the context's tables must be the tables
+    /// the code was compiled with, and the stack must have capacity for the
+    /// block's growth.
+    pub unsafe fn call<I: Io, R: RandomSource>(
+        &self,
+        block: usize,
+        context: *mut Context<'_, '_, I, R>,
+    ) -> u64 {
+        let ptr = self.memory.ptr_at(self.blocks[block].offset);
+
+        // SAFETY: The pointer is the start of a compiled block function.
+        let function: unsafe extern "C" fn(*mut Context<'_, '_, I, R>) -> u64 =
+            unsafe { std::mem::transmute(ptr) };
+
+        // SAFETY: The caller upholds the requirements of the compiled code.
+        unsafe { function(context) }
+    }
+}
+
+/// A block compiled to a function in machine code.
+struct CompiledBlock {
+    /// The offset of the function in the machine code.
+    offset: usize,
+
+    /// The maximum number of values the block may add to the stack.
+    growth: usize,
+
+    /// The block's state, or `None` for the main entry point block.
+    state: Option<State>,
+}
+
+/// Tables of data referenced by compiled code.
+#[derive(Default)]
+pub struct Tables {
+    /// The instructions that are interpreted by a helper function.
+    pub instructions: Vec<Instruction>,
+
+    /// The statuses for the right, down, left, and up targets of random exits.
+    pub random_exits: Vec<[u64; 4]>,
+}
+
+/// The addresses of helper functions called by compiled code.
+struct Helpers {
+    /// The address of the helper for interpreting an instruction.
+    instruction: usize,
+
+    /// The address of the helper for choosing a random exit.
+    random: usize,
+
+    /// The address of the helper for checking the time limit.
+    check_time: usize,
+
+    /// The address of the helper for exceeding a limit.
+    exceed_limit: usize,
+}
+
+impl Helpers {
+    /// Creates new helper addresses for a context type.
+    fn new<I: Io, R: RandomSource>() -> Self {
+        Self {
+            instruction: context::instruction::<I, R> as *const () as usize,
+            random: context::random::<I, R> as *const () as usize,
+            check_time: context::check_time::<I, R> as *const () as usize,
+            exceed_limit: context::exceed_limit::<I, R> as *const () as usize,
+        }
+    }
+}
+
+/// A compiler from blocks to machine code.
+struct Compiler<'a> {
+    /// The assembler.
+    assembler: Assembler,
+
+    /// The statuses for jumping to labels.
+    statuses: BTreeMap<&'a Label, u64>,
+
+    /// The playfield bounds in cells.
+    bounds: (usize, usize),
+
+    /// The limits.
+    limits: Limits,
+
+    /// The tables.
+    tables: &'a mut Tables,
+
+    /// The helper function addresses.
+    helpers: Helpers,
+}
+
+impl Compiler<'_> {
+    /// Compiles a block to a function and returns its offset.
+    fn compile_block(&mut self, block: &Block) -> usize {
+        let offset = self.assembler.position();
+        let steps_exceeded = self.assembler.new_label();
+        let stack_exceeded = self.assembler.new_label();
+        let epilogue = self.assembler.new_label();
+        self.assembler.prologue();
+
+        for instruction in &block.instructions {
+            self.compile_step(steps_exceeded);
+            self.compile_instruction(instruction, epilogue);
+
+            if self.limits.max_stack.is_some() {
+                self.assembler.compare_stack_limit();
+                self.assembler.jump_if(Condition::Above, stack_exceeded);
+            }
+        }
+
+        self.compile_step(steps_exceeded);
+        if self.limits.max_time.is_some() {
+            self.assembler.call(self.helpers.check_time, 0);
+            self.assembler.test_rax();
+            self.assembler.jump_if(Condition::NotZero, epilogue);
+        }
+
+        self.compile_exit(&block.exit);
+        self.assembler.jump(epilogue);
+
+        self.assembler.bind(steps_exceeded);
+        self.assembler.call(self.helpers.exceed_limit, LIMIT_STEPS);
+        self.assembler.jump(epilogue);
+
+        self.assembler.bind(stack_exceeded);
+        self.assembler.call(self.helpers.exceed_limit, LIMIT_STACK);
+
+        self.assembler.bind(epilogue);
+        self.assembler.epilogue();
+        offset
+    }
+
+    /// Compiles counting a step if there is a step limit.
+    fn compile_step(&mut self, steps_exceeded: assembler::Label) {
+        if self.limits.max_steps.is_some() {
+            self.assembler.count_step();
+            self.assembler.jump_if(Condition::Below, steps_exceeded);
+        }
+    }
+
+    /// Compiles an instruction.
+    fn compile_instruction(&mut self, instruction: &Instruction, epilogue: assembler::Label) {
+        let bounds = self.bounds;
+        let asm = &mut self.assembler;
+        match instruction {
+            Instruction::Push(v) => asm.push_imm(v.into_i32()),
+            Instruction::Unary(UnOp::Not) => {
+                asm.pop(Reg::Eax);
+                asm.not();
+                asm.push(Reg::Eax);
+            }
+            Instruction::Binary(
+                o @ (BinOp::Add | BinOp::Subtract | BinOp::Multiply | BinOp::Greater),
+            ) => {
+                asm.pop(Reg::Ecx);
+                asm.pop(Reg::Eax);
+                match o {
+                    BinOp::Add => asm.add(),
+                    BinOp::Subtract => asm.sub(),
+                    BinOp::Multiply => asm.imul(),
+                    _ => asm.greater(),
+                }
+                asm.push(Reg::Eax);
+            }
+            Instruction::Duplicate => {
+                asm.peek();
+                asm.push(Reg::Eax);
+            }
+            Instruction::Swap => {
+                asm.pop(Reg::Eax);
+                asm.pop(Reg::Ecx);
+                asm.push(Reg::Eax);
+                asm.push(Reg::Ecx);
+            }
+            Instruction::Pop => asm.drop_top(),
+            Instruction::GetAt(x, y) if let Some(offset) = cell_offset(*x, *y, bounds) => {
+                asm.load_cell(offset);
+                asm.push(Reg::Eax);
+            }
+            Instruction::PutAt(x, y) if let Some(offset) = cell_offset(*x, *y, bounds) => {
+                asm.pop(Reg::Eax);
+                asm.store_cell(offset);
+            }
+            _ => {
+                // Instructions with side effects or rare edge cases, including
+                // cells outside of the playfield, are interpreted to keep their
+                // behavior identical.
+                let index = u32::try_from(self.tables.instructions.len())
+                    .expect("number of instructions should fit in 32 bits");
+                self.tables.instructions.push(instruction.clone());
+
+                self.assembler.call(self.helpers.instruction, index);
+                self.assembler.test_rax();
+                self.assembler.jump_if(Condition::NotZero, epilogue);
+            }
+        }
+    }
+
+    /// Compiles an exit. The status is left in `eax`.
+    fn compile_exit(&mut self, exit: &Exit) {
+        match exit {
+            Exit::Jump(l) => self.assembler.mov_imm(Reg::Eax, self.status(l)),
+            Exit::Random(r, d, l, u) => {
+                let index = u32::try_from(self.tables.random_exits.len())
+                    .expect("number of random exits should fit in 32 bits");
+                let statuses = [r, d, l, u].map(|l| self.statuses[l]);
+                self.tables.random_exits.push(statuses);
+                self.assembler.call(self.helpers.random, index);
+            }
+            Exit::Branch(t, e) => {
+                let (then_status, else_status) = (self.status(t), self.status(e));
+                self.assembler.pop(Reg::Eax);
+                self.assembler.test_eax();
+                self.assembler.mov_imm(Reg::Eax, else_status);
+                self.assembler.mov_imm(Reg::Ecx, then_status);
+                self.assembler.cmovnz();
+            }
+            Exit::End => self.assembler.mov_imm(Reg::Eax, STATUS_END as u32),
+        }
+    }
+
+    /// Returns the 32-bit status for jumping to a label.
+    fn status(&self, label: &Label) -> u32 {
+        u32::try_from(self.statuses[label]).expect("number of blocks should fit in 32 bits")
+    }
+}
+
+/// Returns the byte offset of a playfield cell from the playfield's cells for
+/// playfield bounds. Returns `None` if the cell is out of bounds.
+fn cell_offset(x: usize, y: usize, (width, height): (usize, usize)) -> Option<i32> {
+    if x >= width || y >= height {
+        return None;
+    }
+
+    let offset = (x + y * width)
+        .checked_mul(4)
+        .and_then(|o| i32::try_from(o).ok())
+        .expect("playfield cell offset should fit in 32 bits");
+    Some(offset)
+}
+
+/// Returns the maximum number of values an instruction may add to the stack.
+fn max_growth(instruction: &Instruction) -> usize {
+    match instruction {
+        Instruction::Swap => 2,
+        Instruction::Push(_)
+        | Instruction::Unary(_)
+        | Instruction::Binary(_)
+        | Instruction::Divide(_)
+        | Instruction::Duplicate
+        | Instruction::Get
+        | Instruction::InputInt
+        | Instruction::InputChar
+        | Instruction::GetAt(_, _) => 1,
+        Instruction::Pop
+        | Instruction::OutputInt
+        | Instruction::OutputChar
+        | Instruction::Put(_)
+        | Instruction::Print(_)
+        | Instruction::PutData
+        | Instruction::PutAt(_, _)
+        | Instruction::PutAtGuarded(_, _, _, _) => 0,
+    }
+}
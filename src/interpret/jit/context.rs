@@ -0,0 +1,265 @@
+use std::{
+    marker::PhantomData,
+    mem::offset_of,
+    ptr::{self, NonNull},
+    time::Instant,
+};
+
+use crate::{
+    common::Value,
+    interpret::{Interpreter, Interrupt, Io, Limit, Limits, RandomSource},
+    ir::{State, state::Direction},
+};
+
+use super::compile::Tables;
+
+/// The byte offset of the stack pointer in a context.
+pub const STACK: u8 = field_offset(offset_of!(Header, stack));
+
+/// The byte offset of the stack length in a context.
+pub const STACK_LEN: u8 = field_offset(offset_of!(Header, stack_len));
+
+/// The byte offset of the playfield cells pointer in a context.
+pub const CELLS: u8 = field_offset(offset_of!(Header, cells));
+
+/// The byte offset of the number of remaining steps in a context.
+pub const REMAINING_STEPS: u8 = field_offset(offset_of!(Header, remaining_steps));
+
+/// The byte offset of the maximum stack length in a context.
+pub const MAX_STACK: u8 = field_offset(offset_of!(Header, max_stack));
+
+/// A status returned by a helper function to continue running a block.
+pub const STATUS_CONTINUE: u64 = 0;
+
+/// A status to recompile the program with the context's recompilation.
+pub const STATUS_RECOMPILE: u64 = 1;
+
+/// A status to stop the program with the context's interrupt.
+pub const STATUS_INTERRUPT: u64 = 2;
+
+/// A status to end the program.
+pub const STATUS_END: u64 = 3;
+
+/// The status to jump to the first block. Statuses for jumping to other blocks
+/// follow in order.
+pub const STATUS_FIRST_BLOCK: u64 = 4;
+
+/// The argument to `exceed_limit` for the step limit.
+pub const LIMIT_STEPS: u32 = 0;
+
+/// The argument to `exceed_limit` for the stack limit.
+pub const LIMIT_STACK: u32 = 1;
+
+/// Converts a field offset to a byte for a displacement in machine code.
+const fn field_offset(offset: usize) -> u8 {
+    assert!(offset < 0x80);
+    offset as u8
+}
+
+/// The part of a context that is accessed directly by machine code.
+#[repr(C)]
+struct Header {
+    /// The pointer to the bottom of the stack.
+    stack: *mut Value,
+
+    /// The length of the stack in values.
+    stack_len: usize,
+
+    /// The pointer to the playfield cells.
+    cells: *mut Value,
+
+    /// The number of steps that may be executed before the step limit is
+    /// exceeded.
+    remaining_steps: u64,
+
+    /// The maximum stack length.
+    max_stack: u64,
+}
+
+/// The state of compiled code while it is running.
+///
+/// Compiled code, helper functions, and the caller all access the context
+/// through the same raw pointer for the whole run, and the interpreter is only
+/// reached through a raw pointer, so no reference to the stack or playfield is
+/// held while compiled code writes to them.
+#[repr(C)]
+pub struct Context<'a, 'b, I: Io, R: RandomSource> {
+    /// The header, which must be the first field.
+    header: Header,
+
+    /// The interpreter for instructions that are not compiled inline.
+    interpreter: NonNull<Interpreter<'a, I, R>>,
+
+    /// The tables of the compiled code.
+    pub tables: Tables,
+
+    /// The state to recompile the program at.
+    pub recompile_state: Option<State>,
+
+    /// The interrupt that stopped the program.
+    pub interrupt: Option<Interrupt>,
+
+    /// The marker for the borrow of the interpreter.
+    marker: PhantomData<&'b mut Interpreter<'a, I, R>>,
+}
+
+impl<'a, 'b, I: Io, R: RandomSource> Context<'a, 'b, I, R> {
+    /// Creates a new context from an interpreter and limits.
+    pub fn new(interpreter: &'b mut Interpreter<'a, I, R>, limits: Limits) -> Self {
+        let max_stack = limits.max_stack.map_or(u64::MAX, |m| m as u64);
+        Self {
+            header: Header {
+                stack: ptr::null_mut(),
+                stack_len: 0,
+                cells: ptr::null_mut(),
+                remaining_steps: limits.max_steps.unwrap_or(u64::MAX),
+                max_stack,
+            },
+            interpreter: NonNull::from(interpreter),
+            tables: Tables::default(),
+            recompile_state: None,
+            interrupt: None,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the interpreter of a context.
+    ///
+    /// # Safety
+    /// The context pointer must be valid, compiled code must not be running
+    /// outside of a helper function, and the returned reference must not be
+    /// used after compiled code resumes.
+    pub unsafe fn interpreter<'c>(context: *mut Self) -> &'c mut Interpreter<'a, I, R> {
+        // SAFETY: The caller upholds that nothing else accesses the
+        // interpreter while the reference is used.
+        unsafe { (*context).interpreter.as_mut() }
+    }
+
+    /// Reserves stack capacity for additional values and prepares a context for
+    /// running a block.
+    ///
+    /// # Safety
+    /// The context pointer must be valid and compiled code must not be running.
+    pub unsafe fn reserve(context: *mut Self, additional: usize) {
+        // SAFETY: The caller upholds the requirements.
+        unsafe {
+            Self::interpreter(context).stack.reserve(additional);
+            Self::sync_to_code(context);
+        }
+    }
+
+    /// Updates the stack length after compiled code has run.
+    ///
+    /// # Safety
+    /// The context pointer must be valid and compiled code must not be running
+    /// outside of a helper function.
+    pub unsafe fn sync_from_code(context: *mut Self) {
+        // SAFETY: Compiled code only writes values below the reserved capacity
+        // and only increases the length over values that it has written.
+        unsafe {
+            let stack_len = (*context).header.stack_len;
+            Self::interpreter(context).stack.set_len(stack_len);
+        }
+    }
+
+    /// Updates the header before compiled code runs.
+    ///
+    /// # Safety
+    /// The context pointer must be valid and compiled code must not be running
+    /// outside of a helper function.
+    unsafe fn sync_to_code(context: *mut Self) {
+        // SAFETY: The caller upholds the requirements, and the pointers are
+        // derived again after every access to the stack or playfield.
+        unsafe {
+            let interpreter = Self::interpreter(context);
+            (*context).header.stack = interpreter.stack.as_mut_ptr();
+            (*context).header.stack_len = interpreter.stack.len();
+            (*context).header.cells = interpreter.playfield.cells_mut().as_mut_ptr();
+        }
+    }
+}
+
+/// Interprets an instruction from the tables.
+pub extern "C" fn instruction<I: Io, R: RandomSource>(
+    context: *mut Context<'_, '_, I, R>,
+    index: u32,
+) -> u64 {
+    // SAFETY: Compiled code passes the context that it was called with and
+    // waits for the helper to return.
+    unsafe {
+        Context::sync_from_code(context);
+
+        let tables = &(*context).tables;
+        let instruction = &tables.instructions[index as usize];
+        let status = match Context::interpreter(context).interpret_instruction(instruction) {
+            Ok(None) => STATUS_CONTINUE,
+            Ok(Some(s)) => {
+                (*context).recompile_state = Some(s);
+                STATUS_RECOMPILE
+            }
+            Err(e) => {
+                (*context).interrupt = Some(Interrupt::Io(e));
+                STATUS_INTERRUPT
+            }
+        };
+
+        Context::sync_to_code(context);
+        status
+    }
+}
+
+/// Returns the status to jump to a random block from a random exit in the
+/// tables.
+pub extern "C" fn random<I: Io, R: RandomSource>(
+    context: *mut Context<'_, '_, I, R>,
+    index: u32,
+) -> u64 {
+    // SAFETY: Compiled code passes the context that it was called with and
+    // waits for the helper to return.
+    unsafe {
+        let tables = &(*context).tables;
+        let [right, down, left, up] = tables.random_exits[index as usize];
+        match Context::interpreter(context).random.next_direction() {
+            Direction::Right => right,
+            Direction::Down => down,
+            Direction::Left => left,
+            Direction::Up => up,
+        }
+    }
+}
+
+/// Checks whether the time limit is exceeded.
+pub extern "C" fn check_time<I: Io, R: RandomSource>(
+    context: *mut Context<'_, '_, I, R>,
+    _: u32,
+) -> u64 {
+    // SAFETY: Compiled code passes the context that it was called with and
+    // waits for the helper to return.
+    unsafe {
+        if Context::interpreter(context)
+            .deadline
+            .is_some_and(|d| Instant::now() >= d)
+        {
+            (*context).interrupt = Some(Interrupt::Limit(Limit::Time));
+            STATUS_INTERRUPT
+        } else {
+            STATUS_CONTINUE
+        }
+    }
+}
+
+/// Stops the program because a limit was exceeded.
+pub extern "C" fn exceed_limit<I: Io, R: RandomSource>(
+    context: *mut Context<'_, '_, I, R>,
+    limit: u32,
+) -> u64 {
+    let limit = match limit {
+        LIMIT_STEPS => Limit::Steps,
+        _ => Limit::Stack,
+    };
+
+    // SAFETY: Compiled code passes the context that it was called with and
+    // waits for the helper to return.
+    unsafe { (*context).interrupt = Some(Interrupt::Limit(limit)) };
+    STATUS_INTERRUPT
+}
@@ -0,0 +1,63 @@
+use std::{io, ptr};
+
+/// A region of executable memory containing machine code.
+pub struct ExecutableMemory {
+    /// The pointer to the start of the region.
+    ptr: *mut u8,
+
+    /// The length of the region in bytes.
+    len: usize,
+}
+
+impl ExecutableMemory {
+    /// Creates new executable memory containing machine code.
+    pub fn new(code: &[u8]) -> io::Result<Self> {
+        let len = code.len().max(1);
+
+        // SAFETY: Mapping new anonymous memory does not affect existing memory.
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        let memory = Self {
+            ptr: ptr.cast(),
+            len,
+        };
+
+        // SAFETY: The region is writable and at least as long as the code.
+        unsafe { ptr::copy_nonoverlapping(code.as_ptr(), memory.ptr, code.len()) };
+
+        // SAFETY: The region was mapped above and is not otherwise referenced.
+        if unsafe { libc::mprotect(ptr, len, libc::PROT_READ | libc::PROT_EXEC) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(memory)
+    }
+
+    /// Returns a pointer to an offset in the machine code.
+    pub fn ptr_at(&self, offset: usize) -> *const u8 {
+        assert!(offset < self.len);
+
+        // SAFETY: The offset was checked to be inside the region.
+        unsafe { self.ptr.add(offset) }
+    }
+}
+
+impl Drop for ExecutableMemory {
+    fn drop(&mut self) {
+        // SAFETY: The region was mapped in `new` and no code in it is running.
+        unsafe { libc::munmap(self.ptr.cast(), self.len) };
+    }
+}
@@ -0,0 +1,138 @@
+//! A just-in-time compiler from programs to x86-64 machine code.
+
+#[cfg(not(all(target_arch = "x86_64", unix)))]
+compile_error!("the `jit` feature is only supported on x86-64 Unix targets");
+
+mod assembler;
+mod compile;
+mod context;
+mod memory;
+
+use compile::{Code, Tables};
+use context::{Context, STATUS_END, STATUS_FIRST_BLOCK, STATUS_INTERRUPT, STATUS_RECOMPILE};
+
+use crate::{
+    common::Playfield,
+    ir::{Program, State},
+    optimize::Options,
+};
+
+use super::{
+    Error, Interpreter, Interrupt, Io, Limits, RandomSource, Result, recompiler::Recompiler,
+};
+
+/// Compiles a program to machine code and runs it with a playfield, an I/O
+/// backend, a random source, and limits. If the program modifies itself, then
+/// it is recompiled and optimized with options.
+pub fn jit_program(
+    program: &Program,
+    playfield: &mut Playfield,
+    io: &mut impl Io,
+    random: &mut impl RandomSource,
+    limits: Limits,
+    options: &Options,
+) -> Result<()> {
+    let bounds = playfield.bounds();
+    let mut interpreter = Interpreter::new(playfield, io, random, limits);
+    let mut context = Context::new(&mut interpreter, limits);
+
+    // SAFETY: The context pointer is the only way the context is accessed until
+    // the program stops.
+    let result = unsafe { run_program(&raw mut context, program, bounds, limits, options) };
+
+    // Always try to flush the output, but report the first error.
+    let flush_result = interpreter.io.flush();
+    result?;
+    Ok(flush_result?)
+}
+
+/// Compiles and runs a program with a context.
+///
+/// # Safety
+/// The context pointer must be valid and must be the only way the context is
+/// accessed while the program runs.
+unsafe fn run_program<I: Io, R: RandomSource>(
+    context: *mut Context<'_, '_, I, R>,
+    program: &Program,
+    bounds: (usize, usize),
+    limits: Limits,
+    options: &Options,
+) -> Result<()> {
+    // SAFETY: The caller upholds the requirements, and compiled code only runs
+    // during calls to blocks.
+    unsafe {
+        let mut code = compile_program(context, program, bounds, limits);
+        let mut main_state = State::default();
+        let mut block = code.main();
+        let mut recompiler = Recompiler::new(options);
+
+        loop {
+            Context::reserve(context, code.growth(block));
+            let status = code.call(block, context);
+            Context::sync_from_code(context);
+
+            match status {
+                STATUS_RECOMPILE => {
+                    let state = (*context)
+                        .recompile_state
+                        .take()
+                        .expect("recompile state should be set");
+
+                    let interpreter = Context::interpreter(context);
+                    let parsed_program = interpreter
+                        .parsed_program
+                        .as_mut()
+                        .expect("program should be parsed");
+
+                    main_state = state.clone();
+                    let program = recompiler.recompile(
+                        state,
+                        parsed_program,
+                        interpreter.playfield,
+                        Program::clone,
+                    );
+                    code = compile_program(context, &program, bounds, limits);
+                    block = code.main();
+                }
+                STATUS_INTERRUPT => {
+                    let interrupt = (*context)
+                        .interrupt
+                        .take()
+                        .expect("interrupt should be set");
+
+                    return Err(match interrupt {
+                        Interrupt::Limit(l) => {
+                            let state = code.state(block).unwrap_or(&main_state).clone();
+                            Error::LimitExceeded(l, state)
+                        }
+                        Interrupt::Io(e) => Error::Io(e),
+                    });
+                }
+                STATUS_END => return Ok(()),
+                _ => {
+                    block =
+                        usize::try_from(status - STATUS_FIRST_BLOCK).expect("block should exist");
+                }
+            }
+        }
+    }
+}
+
+/// Compiles a program and replaces a context's tables.
+///
+/// # Safety
+/// The context pointer must be valid and compiled code must not be running.
+unsafe fn compile_program<I: Io, R: RandomSource>(
+    context: *mut Context<'_, '_, I, R>,
+    program: &Program,
+    bounds: (usize, usize),
+    limits: Limits,
+) -> Code {
+    let mut tables = Tables::default();
+    let code = Code::new::<I, R>(program, bounds, limits, &mut tables)
+        .expect("executable memory should be allocated");
+
+    // SAFETY: The caller upholds the requirements.
+    unsafe { (*context).tables = tables };
+    code
+}
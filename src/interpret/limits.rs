@@ -0,0 +1,42 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+/// Limits on the resources used by an interpreter.
+#[derive(Clone, Copy, Default)]
+pub struct Limits {
+    /// The maximum number of executed instructions and block exits.
+    pub max_steps: Option<u64>,
+
+    /// The maximum number of values on the stack.
+    pub max_stack: Option<usize>,
+
+    /// The maximum wall-clock running time.
+    pub max_time: Option<Duration>,
+}
+
+/// A kind of limit on the resources used by an interpreter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    /// A limit on the number of executed instructions and block exits.
+    Steps,
+
+    /// A limit on the number of values on the stack.
+    Stack,
+
+    /// A limit on the wall-clock running time.
+    Time,
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let data = match self {
+            Self::Steps => "step limit",
+            Self::Stack => "stack limit",
+            Self::Time => "time limit",
+        };
+
+        f.write_str(data)
+    }
+}
@@ -0,0 +1,419 @@
+mod bytecode;
+mod error;
+mod io_backend;
+#[cfg(feature = "jit")]
+mod jit;
+mod limits;
+mod parsed_program;
+mod random;
+mod recompiler;
+mod variables;
+
+pub use error::{Error, Result};
+pub use io_backend::{BufferIo, Io, StdIo};
+#[cfg(feature = "jit")]
+pub use jit::jit_program;
+pub use limits::{Limit, Limits};
+pub use random::{OsRandom, RandomSource, ScriptedRandom, SeededRandom};
+
+use std::{io, rc::Rc, result, time::Instant};
+
+use bytecode::{Bytecode, Op};
+use parsed_program::ParsedProgram;
+use recompiler::Recompiler;
+use variables::Variables;
+
+use crate::{
+    common::{Playfield, Value},
+    ir::{Instruction, Program, State, ops::BinOp, state::Direction},
+    optimize::Options,
+    parse,
+};
+
+/// Interprets a program with a playfield, an I/O backend, a random source, and
+/// limits. If the program modifies itself, then it is recompiled and optimized
+/// with options.
+pub fn interpret_program(
+    program: &Program,
+    playfield: &mut Playfield,
+    io: &mut impl Io,
+    random: &mut impl RandomSource,
+    limits: Limits,
+    options: &Options,
+) -> Result<()> {
+    Interpreter::new(playfield, io, random, limits).interpret_program(program, options)
+}
+
+/// A high-level interpreter for potentially self-modifying programs.
+struct Interpreter<'a, I: Io, R: RandomSource> {
+    /// The playfield.
+    playfield: &'a mut Playfield,
+
+    /// The I/O backend.
+    io: &'a mut I,
+
+    /// The random source.
+    random: &'a mut R,
+
+    /// The stack.
+    stack: Vec<Value>,
+
+    /// The limits.
+    limits: Limits,
+
+    /// The number of executed instructions and block exits.
+    steps: u64,
+
+    /// The instant when the time limit is exceeded.
+    deadline: Option<Instant>,
+
+    /// The program parsed without optimization from the playfield. It is
+    /// parsed from the state after the first put instruction that changes a
+    /// cell, and is used to check whether later changes affect the program.
+    /// Cells written without recompiling the program are reparsed when it is
+    /// next recompiled.
+    parsed_program: Option<ParsedProgram>,
+}
+
+impl<'a, I: Io, R: RandomSource> Interpreter<'a, I, R> {
+    /// Creates a new interpreter from a playfield, an I/O backend, a random
+    /// source, and limits.
+    fn new(playfield: &'a mut Playfield, io: &'a mut I, random: &'a mut R, limits: Limits) -> Self {
+        Self {
+            playfield,
+            io,
+            random,
+            stack: Vec::new(),
+            limits,
+            steps: 0,
+            deadline: limits.max_time.map(|t| Instant::now() + t),
+            parsed_program: None,
+        }
+    }
+
+    /// Interprets a program by lowering it to bytecode and running it. If the
+    /// program modifies itself, then it is recompiled and optimized with
+    /// options.
+    fn interpret_program(&mut self, program: &Program, options: &Options) -> Result<()> {
+        let mut bytecode = Rc::new(Bytecode::new(program));
+        let mut variables = Variables::new(bytecode.variables(), self.playfield);
+        let mut main_state = State::default();
+        let mut offset = bytecode.entry();
+        let mut recompiler = Recompiler::new(options);
+
+        let result = loop {
+            match self.interpret_op(bytecode.op(offset), &mut variables) {
+                Ok(Flow::Next) => offset += 1,
+                Ok(Flow::Jump(o)) => offset = o,
+                Ok(Flow::Recompile(s)) => {
+                    let parsed_program = self
+                        .parsed_program
+                        .as_mut()
+                        .expect("program should be parsed");
+
+                    main_state = s.clone();
+                    variables.store(self.playfield);
+                    bytecode =
+                        recompiler.recompile(s, parsed_program, self.playfield, Bytecode::new);
+                    variables = Variables::new(bytecode.variables(), self.playfield);
+                    offset = bytecode.entry();
+                }
+                Ok(Flow::End) => break Ok(()),
+                Err(Interrupt::Limit(l)) => {
+                    let state = bytecode.state_at(offset).unwrap_or(&main_state).clone();
+                    break Err(Error::LimitExceeded(l, state));
+                }
+                Err(Interrupt::Io(e)) => break Err(Error::Io(e)),
+            }
+        };
+
+        variables.store(self.playfield);
+
+        // Always try to flush the output, but report the first error.
+        let flush_result = self.io.flush();
+        result?;
+        Ok(flush_result?)
+    }
+
+    /// Interprets an operation with static variables and returns the control
+    /// flow from the operation.
+    fn interpret_op(
+        &mut self,
+        op: &Op,
+        variables: &mut Variables,
+    ) -> result::Result<Flow, Interrupt> {
+        self.step()?;
+
+        // The time limit is only checked at block exits to keep instructions
+        // fast.
+        let is_exit = !matches!(
+            op,
+            Op::Instruction(_) | Op::Load(_) | Op::Store(_) | Op::GuardedStore(_, _, _)
+        );
+        if is_exit && self.deadline.is_some_and(|d| Instant::now() >= d) {
+            return Err(Interrupt::Limit(Limit::Time));
+        }
+
+        let offset = match op {
+            Op::Instruction(i) => {
+                // Dynamic gets and puts may access the cells of static
+                // variables, so the variables are synchronized with the
+                // playfield around them.
+                if matches!(
+                    i,
+                    Instruction::Get | Instruction::Put(_) | Instruction::PutData
+                ) {
+                    variables.store(self.playfield);
+                }
+
+                let recompile_state = self.interpret_instruction(i)?;
+                if matches!(i, Instruction::Put(_) | Instruction::PutData) {
+                    variables.load(self.playfield);
+                }
+
+                if let Some(state) = recompile_state {
+                    return Ok(Flow::Recompile(state));
+                }
+
+                return self.check_stack().map(|()| Flow::Next);
+            }
+            Op::Load(slot) => {
+                self.push(variables.get(*slot));
+                return self.check_stack().map(|()| Flow::Next);
+            }
+            Op::Store(slot) => {
+                let value = self.pop();
+                variables.set(*slot, value);
+                return Ok(Flow::Next);
+            }
+            Op::GuardedStore(slot, mode, state) => {
+                let value = self.pop();
+                // The guard only checks the mode that the cell is reached in
+                // by this program. Blocks parsed for the cell in other modes
+                // are reparsed before the next recompilation.
+                if !parse::changes_parse(*mode, variables.get(*slot), value) {
+                    variables.set(*slot, value);
+                    return Ok(Flow::Next);
+                }
+
+                // The program is parsed from the playfield before the cell
+                // changes, so the variables are synchronized around the put.
+                variables.store(self.playfield);
+                let (x, y) = variables.position(*slot);
+                let recompile_state = self.put(x, y, value, state);
+                variables.load(self.playfield);
+                return Ok(recompile_state.map_or(Flow::Next, Flow::Recompile));
+            }
+            Op::Jump(o) => *o,
+            Op::Random(r, d, l, u) => match self.random.next_direction() {
+                Direction::Right => *r,
+                Direction::Down => *d,
+                Direction::Left => *l,
+                Direction::Up => *u,
+            },
+            Op::Branch(t, e) => {
+                if self.pop().into_i32() != 0 {
+                    *t
+                } else {
+                    *e
+                }
+            }
+            Op::End => return Ok(Flow::End),
+        };
+
+        Ok(Flow::Jump(offset))
+    }
+
+    /// Checks whether the stack limit is exceeded.
+    fn check_stack(&self) -> result::Result<(), Interrupt> {
+        if self.limits.max_stack.is_some_and(|m| self.stack.len() > m) {
+            Err(Interrupt::Limit(Limit::Stack))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Counts an executed instruction or block exit.
+    fn step(&mut self) -> result::Result<(), Interrupt> {
+        self.steps += 1;
+        if self.limits.max_steps.is_some_and(|m| self.steps > m) {
+            Err(Interrupt::Limit(Limit::Steps))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Interprets an instruction and returns the state to recompile the
+    /// program at. Returns `None` if the program should not be recompiled.
+    fn interpret_instruction(&mut self, instruction: &Instruction) -> io::Result<Option<State>> {
+        match instruction {
+            Instruction::Push(v) => self.push(*v),
+            Instruction::Unary(o) => {
+                let rhs = self.pop();
+                self.push(o.eval(rhs));
+            }
+            Instruction::Binary(o) => {
+                let rhs = self.pop();
+                let lhs = self.pop();
+                self.push(o.eval(lhs, rhs));
+            }
+            Instruction::Divide(o) => {
+                let rhs = self.pop();
+                let lhs = self.pop();
+                if rhs.into_i32() != 0 {
+                    self.push(BinOp::from(*o).eval(lhs, rhs));
+                } else {
+                    let prompt = format!("What do you want {}{o}0 to be? ", lhs.into_i32());
+                    self.io.output_str(&prompt)?;
+                    self.input_int()?;
+                }
+            }
+            Instruction::Duplicate => self.push(self.peek()),
+            Instruction::Swap => {
+                let top = self.pop();
+                let under = self.pop();
+                self.push(top);
+                self.push(under);
+            }
+            Instruction::Pop => {
+                self.pop();
+            }
+            Instruction::OutputInt => {
+                let value = self.pop().into_i32();
+                self.io.output_int(value)?;
+            }
+            Instruction::OutputChar => {
+                let value = self.pop().into_char_lossy();
+                self.io.output_char(value)?;
+            }
+            Instruction::Get => {
+                let y = self.pop().into_i32();
+                let x = self.pop().into_i32();
+                let value = match self.playfield.position(x, y) {
+                    Some((x, y)) => self.playfield.get(x, y).unwrap_or_default(),
+                    None => Value::default(),
+                };
+                self.push(value);
+            }
+            Instruction::Put(s) => {
+                let y = self.pop().into_i32();
+                let x = self.pop().into_i32();
+                let value = self.pop();
+                if let Some((x, y)) = self.playfield.position(x, y) {
+                    return Ok(self.put(x, y, value, s));
+                }
+            }
+            Instruction::InputInt => self.input_int()?,
+            Instruction::InputChar => {
+                let value = self.io.input_char()?.map_or(Value::from(-1), Into::into);
+                self.push(value);
+            }
+            Instruction::Print(s) => self.io.output_str(s)?,
+            Instruction::GetAt(x, y) => self.push(self.playfield.get(*x, *y).unwrap_or_default()),
+            Instruction::PutAt(x, y) => {
+                let value = self.pop();
+                self.playfield.put(*x, *y, value);
+            }
+            Instruction::PutData => {
+                let y = self.pop().into_i32();
+                let x = self.pop().into_i32();
+                let value = self.pop();
+                if let Some((x, y)) = self.playfield.position(x, y) {
+                    self.playfield.put(x, y, value);
+                }
+            }
+            Instruction::PutAtGuarded(x, y, m, s) => {
+                let value = self.pop();
+                let previous_value = self
+                    .playfield
+                    .get(*x, *y)
+                    .expect("position should be in bounds");
+
+                if parse::changes_parse(*m, previous_value, value) {
+                    return Ok(self.put(*x, *y, value, s));
+                }
+
+                // Blocks parsed for the cell in other modes are reparsed
+                // before the next recompilation, like any other changed cell.
+                self.playfield.put(*x, *y, value);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Stores a value in the playfield at a position from a put instruction
+    /// that continues at a state. Returns the state if the change modifies the
+    /// program, so the program should be recompiled from it.
+    fn put(&mut self, x: usize, y: usize, value: Value, state: &State) -> Option<State> {
+        let previous_value = self.playfield.get(x, y)?;
+        if previous_value == value {
+            return None;
+        }
+
+        // The program is parsed from the state after the put before the cell
+        // changes. The cell may have been written since the program was parsed
+        // without recompiling it, so the value is compared with the value that
+        // the cell was parsed from instead of its current value. Every changed
+        // cell is reparsed when the program is recompiled.
+        let parsed_program = self
+            .parsed_program
+            .get_or_insert_with(|| ParsedProgram::new(self.playfield, state.clone()));
+
+        let changes_program = parsed_program.changes((x, y), value);
+        self.playfield.put(x, y, value);
+        changes_program.then(|| state.clone())
+    }
+
+    /// Parses an integer from a line of user input and pushes it to the stack.
+    fn input_int(&mut self) -> io::Result<()> {
+        let value = self.io.input_int()?.unwrap_or(-1);
+        self.push(value.into());
+        Ok(())
+    }
+
+    /// Returns the top value of the stack.
+    fn peek(&self) -> Value {
+        self.stack.last().copied().unwrap_or_default()
+    }
+
+    /// Pushes a value to the stack.
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    /// Pops a value from the stack.
+    fn pop(&mut self) -> Value {
+        self.stack.pop().unwrap_or_default()
+    }
+}
+
+/// A control flow from an operation.
+enum Flow {
+    /// A continuation to the next operation.
+    Next,
+
+    /// A jump to an offset.
+    Jump(usize),
+
+    /// A recompilation at a state.
+    Recompile(State),
+
+    /// A program ending.
+    End,
+}
+
+/// An interruption while interpreting an operation.
+enum Interrupt {
+    /// An interruption caused by a limit being exceeded.
+    Limit(Limit),
+
+    /// An interruption caused by an I/O error.
+    Io(io::Error),
+}
+
+impl From<io::Error> for Interrupt {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
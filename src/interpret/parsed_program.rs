@@ -0,0 +1,63 @@
+use crate::{
+    common::{Playfield, Value},
+    ir::{Program, State},
+    parse,
+};
+
+/// A program parsed without optimization from a playfield, with the playfield
+/// cells that it was parsed from. Cells may be written without recompiling the
+/// program, so it is synchronized with the playfield before it is recompiled.
+pub struct ParsedProgram {
+    /// The program.
+    program: Program,
+
+    /// The playfield cells in row-major order that the program was parsed
+    /// from.
+    cells: Box<[Value]>,
+
+    /// The width of the playfield in cells.
+    width: usize,
+}
+
+impl ParsedProgram {
+    /// Parses a new program from a playfield and a main state.
+    pub fn new(playfield: &Playfield, main_state: State) -> Self {
+        Self {
+            program: parse::parse_program_state(playfield, main_state).0,
+            cells: playfield.cells().into(),
+            width: playfield.bounds().0,
+        }
+    }
+
+    /// Returns the program.
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Returns whether storing a value in the cell at a position would change
+    /// the program. The value is compared with the value that the cell was
+    /// parsed from, which may differ from the cell's current value if it was
+    /// written without recompiling the program.
+    pub fn changes(&self, position: (usize, usize), value: Value) -> bool {
+        let (x, y) = position;
+        let parsed_value = self.cells[x + y * self.width];
+        parse::changes_program(&self.program, position, parsed_value, value)
+    }
+
+    /// Reparses the program from every cell that changed in a playfield since
+    /// it was parsed, with a new main state.
+    pub fn sync(&mut self, playfield: &Playfield, main_state: State) {
+        let width = self.width;
+        let positions: Vec<_> = self
+            .cells
+            .iter()
+            .zip(playfield.cells())
+            .enumerate()
+            .filter(|(_, (parsed, current))| parsed != current)
+            .map(|(i, _)| (i % width, i / width))
+            .collect();
+
+        parse::reparse_program(&mut self.program, playfield, positions, main_state);
+        self.cells.copy_from_slice(playfield.cells());
+    }
+}
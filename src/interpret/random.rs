@@ -0,0 +1,81 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::ir::state::Direction;
+
+/// A source of random directions for an interpreter.
+pub trait RandomSource {
+    /// Returns the next random direction.
+    fn next_direction(&mut self) -> Direction;
+}
+
+/// A random source seeded by the operating system.
+#[derive(Default)]
+pub struct OsRandom;
+
+impl RandomSource for OsRandom {
+    fn next_direction(&mut self) -> Direction {
+        direction_from_bits(rand::random())
+    }
+}
+
+/// A random source using a seeded pseudorandom number generator.
+pub struct SeededRandom {
+    /// The inner pseudorandom number generator.
+    rng: StdRng,
+}
+
+impl SeededRandom {
+    /// Creates a new seeded random source from a seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn next_direction(&mut self) -> Direction {
+        direction_from_bits(self.rng.random())
+    }
+}
+
+/// A random source that repeats a fixed sequence of directions.
+#[derive(Clone)]
+pub struct ScriptedRandom {
+    /// The directions.
+    directions: Box<[Direction]>,
+
+    /// The index of the next direction.
+    index: usize,
+}
+
+impl ScriptedRandom {
+    /// Creates a new scripted random source from a sequence of directions.
+    /// Returns `None` if the sequence is empty.
+    pub fn new(directions: impl IntoIterator<Item = Direction>) -> Option<Self> {
+        let directions: Box<[Direction]> = directions.into_iter().collect();
+        (!directions.is_empty()).then_some(Self {
+            directions,
+            index: 0,
+        })
+    }
+}
+
+impl RandomSource for ScriptedRandom {
+    fn next_direction(&mut self) -> Direction {
+        let direction = self.directions[self.index];
+        self.index = (self.index + 1) % self.directions.len();
+        direction
+    }
+}
+
+/// Converts the lowest two bits of a random value to a direction.
+fn direction_from_bits(bits: u32) -> Direction {
+    match bits & 0b11 {
+        0b00 => Direction::Right,
+        0b01 => Direction::Down,
+        0b10 => Direction::Left,
+        0b11 => Direction::Up,
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    collections::VecDeque,
+    hash::{DefaultHasher, Hash, Hasher},
+    rc::Rc,
+};
+
+use crate::{
+    common::{Playfield, Value},
+    ir::{Program, State},
+    optimize::{self, Level, Options},
+    parse::FlowGraph,
+};
+
+use super::parsed_program::ParsedProgram;
+
+/// The maximum number of recompiled programs in a recompiler's cache.
+const MAX_CACHED_PROGRAMS: usize = 16;
+
+/// The total optimization effort that a recompiler may spend. Optimizing a
+/// program costs the square of its number of parsed blocks, since optimization
+/// time grows faster than linearly with the size of a program.
+const OPTIMIZATION_BUDGET: usize = 1 << 26;
+
+/// A recompiler for programs that modify themselves. Recompiled programs are
+/// optimized within an effort budget, lowered, and cached by the playfield
+/// contents and the state that they were recompiled at.
+pub struct Recompiler<T> {
+    /// The options for optimizing recompiled programs.
+    options: Options,
+
+    /// The optimization effort that may still be spent. Programs that would
+    /// exceed it are lowered without optimization, so programs that keep
+    /// changing to new playfield contents do not spend most of their time
+    /// being optimized.
+    remaining_budget: usize,
+
+    /// The cached programs, from oldest to newest.
+    cached_programs: VecDeque<CachedProgram<T>>,
+}
+
+impl<T> Recompiler<T> {
+    /// Creates a new recompiler from the options that the original program was
+    /// optimized with. Recompiled programs are optimized with at most one
+    /// optimization pass.
+    pub fn new(options: &Options) -> Self {
+        let level = match options.level {
+            Level::None => Level::None,
+            Level::Once | Level::Full => Level::Once,
+        };
+
+        Self {
+            options: Options {
+                level,
+                disabled_steps: options.disabled_steps.clone(),
+            },
+            remaining_budget: OPTIMIZATION_BUDGET,
+            cached_programs: VecDeque::new(),
+        }
+    }
+
+    /// Recompiles a program at a state from a parsed program, which is first
+    /// synchronized with a playfield. If the program is not cached, then it is
+    /// optimized if the budget allows it, lowered with a function, and cached.
+    pub fn recompile(
+        &mut self,
+        state: State,
+        parsed_program: &mut ParsedProgram,
+        playfield: &Playfield,
+        lower: impl FnOnce(&Program) -> T,
+    ) -> Rc<T> {
+        parsed_program.sync(playfield, state.clone());
+        let parsed_program = parsed_program.program();
+        let hash = hash_playfield(playfield, &state);
+        if let Some(cached_program) = self
+            .cached_programs
+            .iter()
+            .find(|c| c.hash == hash && c.state == state && *c.cells == *playfield.cells())
+        {
+            return Rc::clone(&cached_program.program);
+        }
+
+        let cost = parsed_program.blocks.len().saturating_pow(2);
+        let program = if cost <= self.remaining_budget {
+            self.remaining_budget -= cost;
+            let mut program = parsed_program.clone();
+            let flow_graph = FlowGraph::from_program(&program);
+            optimize::optimize_program_observed(
+                &mut program,
+                &flow_graph,
+                playfield,
+                &self.options,
+                |_, _| {},
+            );
+
+            Rc::new(lower(&program))
+        } else {
+            Rc::new(lower(parsed_program))
+        };
+
+        if self.cached_programs.len() == MAX_CACHED_PROGRAMS {
+            self.cached_programs.pop_front();
+        }
+
+        self.cached_programs.push_back(CachedProgram {
+            hash,
+            state,
+            cells: playfield.cells().into(),
+            program: Rc::clone(&program),
+        });
+
+        program
+    }
+}
+
+/// A recompiled program in a recompiler's cache.
+struct CachedProgram<T> {
+    /// The hash of the playfield contents and the state.
+    hash: u64,
+
+    /// The state that the program was recompiled at.
+    state: State,
+
+    /// The playfield cells that the program was recompiled from. They are
+    /// compared on lookup so that hash collisions never run the wrong program.
+    cells: Box<[Value]>,
+
+    /// The lowered program.
+    program: Rc<T>,
+}
+
+/// Returns the hash of a playfield's contents and a state.
+fn hash_playfield(playfield: &Playfield, state: &State) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    playfield.bounds().hash(&mut hasher);
+    playfield.cells().hash(&mut hasher);
+    state.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,66 @@
+use crate::common::{Playfield, Value};
+
+/// Static variables for playfield cells that are accessed at known positions.
+/// Values are read from and written to slots instead of the playfield, and are
+/// only synchronized with the playfield when it is accessed another way.
+pub struct Variables {
+    /// The values in slot order.
+    values: Vec<Value>,
+
+    /// The positions of the cells in slot order.
+    positions: Vec<(usize, usize)>,
+
+    /// Whether any values were changed since they were stored to the
+    /// playfield.
+    is_dirty: bool,
+}
+
+impl Variables {
+    /// Creates new variables for cell positions in slot order with the values
+    /// of the cells from a playfield.
+    pub fn new(positions: &[(usize, usize)], playfield: &Playfield) -> Self {
+        let mut variables = Self {
+            values: vec![Value::default(); positions.len()],
+            positions: positions.to_vec(),
+            is_dirty: false,
+        };
+
+        variables.load(playfield);
+        variables
+    }
+
+    /// Returns the value in a slot.
+    pub fn get(&self, slot: usize) -> Value {
+        self.values[slot]
+    }
+
+    /// Returns the position of the cell of a slot.
+    pub fn position(&self, slot: usize) -> (usize, usize) {
+        self.positions[slot]
+    }
+
+    /// Sets the value in a slot.
+    pub fn set(&mut self, slot: usize, value: Value) {
+        self.values[slot] = value;
+        self.is_dirty = true;
+    }
+
+    /// Reads the values from the cells in a playfield.
+    pub fn load(&mut self, playfield: &Playfield) {
+        for (value, &(x, y)) in self.values.iter_mut().zip(&self.positions) {
+            *value = playfield.get(x, y).expect("position should be in bounds");
+        }
+    }
+
+    /// Writes the values to the cells in a playfield if any values were
+    /// changed.
+    pub fn store(&mut self, playfield: &mut Playfield) {
+        if self.is_dirty {
+            for (&value, &(x, y)) in self.values.iter().zip(&self.positions) {
+                playfield.put(x, y, value);
+            }
+
+            self.is_dirty = false;
+        }
+    }
+}
@@ -3,6 +3,7 @@ use std::fmt::{self, Display, Formatter};
 use super::{Exit, Instruction};
 
 /// A linear sequence of instructions with a single exit.
+#[derive(Clone)]
 pub struct Block {
     /// The instructions.
     pub instructions: Vec<Instruction>,
@@ -5,6 +5,7 @@ use crate::common::Value;
 use super::{
     State,
     ops::{BinOp, DivOp, UnOp},
+    state::Mode,
 };
 
 /// An instruction in a block.
@@ -83,6 +84,19 @@ pub enum Instruction {
     /// playfield at known, in-bounds, non-modifying coordinates.
     /// `[...][value]` -> `[...]`
     PutAt(usize, usize),
+
+    /// An instruction to pop two coordinate values and a stored value from the
+    /// stack and store the stored value in the playfield at the coordinates,
+    /// which are known to be non-modifying.
+    /// `[...][value][x][y]` -> `[...]`
+    PutData,
+
+    /// An instruction to pop a value from the stack and store it in the
+    /// playfield at known, in-bounds coordinates that are reached in a mode. If
+    /// the value changes how the cell is parsed in the mode, then the program
+    /// is recompiled at a state.
+    /// `[...][value]` -> `[...]`
+    PutAtGuarded(usize, usize, Mode, State),
 }
 
 impl Display for Instruction {
@@ -104,6 +118,10 @@ impl Display for Instruction {
             Self::Print(s) => return write!(f, "{:8}\"{}\"", "print", s.escape_default()),
             Self::GetAt(x, y) => return write!(f, "{:8}{x}, {y}", "getat"),
             Self::PutAt(x, y) => return write!(f, "{:8}{x}, {y}", "putat"),
+            Self::PutData => "putdata",
+            Self::PutAtGuarded(x, y, m, s) => {
+                return write!(f, "{:8}{x}, {y}, {m}, {s}", "putgrd");
+            }
         };
         f.write_str(data)
     }
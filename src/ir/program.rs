@@ -6,6 +6,7 @@ use std::{
 use super::{Block, Label};
 
 /// A Befunge program.
+#[derive(Clone)]
 pub struct Program {
     /// The blocks.
     pub blocks: BTreeMap<Label, Block>,
@@ -4,7 +4,7 @@ use std::fmt::{self, Display, Formatter};
 // Do not change the field order to be more 'pretty' - it allows the `Ord` trait
 // to sort states in a user-friendly order. Ordering states also allows
 // compilation and debug dumps to be deterministic.
-#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct State {
     /// The Y coordinate in cells from the top edge of a playfield.
     pub y: usize,
@@ -37,7 +37,7 @@ impl Display for State {
 }
 
 /// A Befunge program counter's mode.
-#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Mode {
     /// A mode where playfield values are executed as commands.
     #[default]
@@ -59,7 +59,7 @@ impl Display for Mode {
 }
 
 /// A Befunge program counter's direction.
-#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Direction {
     /// A direction where the X coordinate is incremented.
     #[default]
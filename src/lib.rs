@@ -0,0 +1,30 @@
+//! Fungus is an optimizing [Befunge](https://esolangs.org/wiki/Befunge)
+//! interpreter. Befunge source code is loaded into a [`Playfield`], parsed into
+//! a control flow graph of [`Block`]s (a [`Program`]), optimized, and then
+//! interpreted.
+//!
+//! The [`Builder`] type runs the whole pipeline:
+//! ```no_run
+//! let compiled = fungus::Builder::new("\"!olleH\",,,,,,@").compile();
+//! compiled.run().expect("program should not exceed any limits");
+//! ```
+//!
+//! The individual stages are also available from the [`parse`], [`optimize`],
+//! and [`interpret`] modules. The [`codegen`] module generates source code in
+//! other languages from a compiled program, and the [`cache`] module stores
+//! compiled programs so that they do not need to be compiled again.
+
+mod builder;
+
+pub mod cache;
+pub mod codegen;
+pub mod common;
+pub mod dump;
+pub mod interpret;
+pub mod ir;
+pub mod optimize;
+pub mod parse;
+
+pub use builder::{Builder, Compiled, Stage};
+pub use common::{Playfield, Standard, Value};
+pub use ir::{Block, Exit, Instruction, Label, Program, State};
@@ -1,16 +1,20 @@
-mod common;
 mod config;
 mod error;
-mod interpret;
-mod ir;
-mod optimize;
-mod parse;
 
-use std::{fs, path::Path, process::ExitCode};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    process::ExitCode,
+};
 
-use common::Playfield;
-use config::Config;
+use config::{Config, Emit, Source};
 use error::{Error, Result};
+use fungus::{
+    Builder, Compiled, Playfield, Stage,
+    cache::Cache,
+    codegen, dump,
+    interpret::{OsRandom, RandomSource, SeededRandom, StdIo},
+};
 
 /// Runs Fungus and returns an exit code.
 fn main() -> ExitCode {
@@ -23,30 +27,117 @@ fn main() -> ExitCode {
 /// Runs Fungus.
 fn try_run() -> Result<()> {
     let config = Config::try_new()?;
-    let mut playfield = try_load_playfield(config.path())?;
-    let (mut program, flow_graph) = parse::parse_program(&playfield);
-    optimize::optimize_program(&mut program, &flow_graph, &playfield);
+    let source = config.source();
+    let playfield = Playfield::new(&try_read_source(&source)?, config.standard());
+    let mut builder = Builder::from_playfield(playfield)
+        .options(config.options())
+        .limits(config.limits());
 
-    if config.dump() {
-        println!("{program}");
+    if let Some(dir) = config.cache_dir() {
+        builder = builder.cache(Cache::new(dir));
+    }
+
+    let compiled = try_compile(builder, config.emits())?;
+
+    if config.stats() {
+        // Statistics are printed to the standard error stream so that they do
+        // not mix with the program's output.
+        let _ = writeln!(io::stderr(), "{}", compiled.stats());
+    }
+
+    // The standard input stream has already been read to its end if it was
+    // used for source code, so treat it as having no user input.
+    let mut io = match source {
+        Source::Stdin => StdIo::without_input(),
+        Source::File(_) | Source::Eval(_) => StdIo::default(),
+    };
+
+    if let Some(format) = config.dump_format() {
+        let dump = dump::dump_program(compiled.program(), format);
+        writeln!(io::stdout(), "{dump}").map_err(Error::CouldNotWriteOutput)?;
+    } else if config.emits().iter().any(|e| e.target().is_some()) {
+        // Generated source code is printed instead of interpreting the program.
+        for target in config.emits().iter().filter_map(|e| e.target()) {
+            let code = codegen::generate_program(compiled.program(), compiled.playfield(), target)?;
+            io::stdout()
+                .write_all(code.as_bytes())
+                .map_err(Error::CouldNotWriteOutput)?;
+        }
+    } else if let Some(mut random) = config.random_script() {
+        try_run_compiled(compiled, &config, &mut io, &mut random)?;
+    } else if let Some(seed) = config.seed() {
+        try_run_compiled(compiled, &config, &mut io, &mut SeededRandom::new(seed))?;
     } else {
-        interpret::interpret_program(&program, &mut playfield);
+        try_run_compiled(compiled, &config, &mut io, &mut OsRandom)?;
+    }
+
+    Ok(())
+}
+
+/// Runs a compiled program with an I/O backend and a random source.
+#[cfg_attr(not(feature = "jit"), allow(unused_variables))]
+fn try_run_compiled(
+    compiled: Compiled,
+    config: &Config,
+    io: &mut StdIo,
+    random: &mut impl RandomSource,
+) -> Result<()> {
+    #[cfg(feature = "jit")]
+    if config.jit() {
+        compiled.run_jit_with(io, random)?;
+        return Ok(());
     }
 
+    compiled.run_with(io, random)?;
     Ok(())
 }
 
-/// Loads a playfield from a file path.
-fn try_load_playfield(path: &Path) -> Result<Playfield> {
-    let source = try_read_source(path)?;
-    Ok(Playfield::new(&source))
+/// Compiles a program with a builder and prints it after compilation stages.
+fn try_compile(builder: Builder, emits: &[Emit]) -> Result<Compiled> {
+    if emits.is_empty() {
+        return Ok(builder.compile());
+    }
+
+    let mut result = Ok(());
+    let compiled = builder.compile_observed(|stage, program| {
+        if let (Ok(()), Some(header)) = (&result, stage_header(stage, emits)) {
+            result = writeln!(io::stdout(), "== {header} ==\n{program}\n");
+        }
+    });
+
+    result.map_err(Error::CouldNotWriteOutput)?;
+    Ok(compiled)
 }
 
-/// Reads source code from a file path.
-fn try_read_source(path: &Path) -> Result<String> {
-    if path.is_file() {
-        fs::read_to_string(path).map_err(Error::CouldNotReadSourceFile)
-    } else {
-        Err(Error::SourceFileDoesNotExist)
+/// Returns the header to print before the program after a compilation stage.
+/// Returns `None` if the program should not be printed after the stage.
+fn stage_header(stage: Stage, emits: &[Emit]) -> Option<String> {
+    match stage {
+        Stage::Parse if emits.contains(&Emit::Parse) => Some("parse".into()),
+        Stage::Step(r)
+            if emits.contains(&Emit::Steps) || r.changed && emits.contains(&Emit::ChangedSteps) =>
+        {
+            let unchanged = if r.changed { "" } else { " (unchanged)" };
+            Some(format!("pass {}: {}{unchanged}", r.pass, r.step))
+        }
+        Stage::Parse | Stage::Step(_) => None,
+    }
+}
+
+/// Reads source code from a source code input.
+fn try_read_source(source: &Source) -> Result<String> {
+    match source {
+        Source::File(path) if path.is_file() => {
+            fs::read_to_string(path).map_err(Error::CouldNotReadSourceFile)
+        }
+        Source::File(_) => Err(Error::SourceFileDoesNotExist),
+        Source::Stdin => {
+            let mut source = String::new();
+            io::stdin()
+                .read_to_string(&mut source)
+                .map_err(Error::CouldNotReadSourceStdin)?;
+            Ok(source)
+        }
+        Source::Eval(rows) => Ok(rows.join("\n")),
     }
 }
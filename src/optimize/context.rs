@@ -1,10 +1,33 @@
-use crate::{common::Playfield, ir::State, parse::FlowGraph};
+use std::ops::RangeInclusive;
+
+use crate::{
+    common::{Playfield, Value},
+    ir::{State, state::Mode},
+    parse::FlowGraph,
+};
+
+use super::{Level, Options, Step};
 
 /// Context for optimizing a program.
 pub struct Context<'a> {
     /// Whether an optimization pass should be run.
     should_run_pass: bool,
 
+    /// The number of changes made to the program.
+    changes: usize,
+
+    /// The optimization step that is being run.
+    step: Option<Step>,
+
+    /// The number of changes made to the program by each optimization step.
+    step_changes: [usize; Step::ALL.len()],
+
+    /// The number of optimization passes that have been run.
+    passes: usize,
+
+    /// The options.
+    options: &'a Options,
+
     /// The flow graph.
     flow_graph: &'a FlowGraph,
 
@@ -13,10 +36,15 @@ pub struct Context<'a> {
 }
 
 impl<'a> Context<'a> {
-    /// Creates a new context from a flow graph and a playfield.
-    pub fn new(flow_graph: &'a FlowGraph, playfield: &'a Playfield) -> Self {
+    /// Creates a new context from a flow graph, a playfield, and options.
+    pub fn new(flow_graph: &'a FlowGraph, playfield: &'a Playfield, options: &'a Options) -> Self {
         Self {
             should_run_pass: true,
+            changes: 0,
+            step: None,
+            step_changes: [0; Step::ALL.len()],
+            passes: 0,
+            options,
             flow_graph,
             playfield,
         }
@@ -24,24 +52,80 @@ impl<'a> Context<'a> {
 
     /// Returns whether an optimization pass should be run.
     pub fn should_run_pass(&mut self) -> bool {
-        let should_run_pass = self.should_run_pass;
+        let should_run_pass = self.should_run_pass
+            && match self.options.level {
+                Level::None => false,
+                Level::Once => self.passes == 0,
+                Level::Full => true,
+            };
 
         // Do not run another pass unless changes are made.
         self.should_run_pass = false;
 
+        if should_run_pass {
+            self.passes += 1;
+        }
         should_run_pass
     }
 
+    /// Returns the number of optimization passes that have been run.
+    pub fn passes(&self) -> usize {
+        self.passes
+    }
+
+    /// Returns whether an optimization step is enabled.
+    pub fn is_step_enabled(&self, step: Step) -> bool {
+        self.options.is_step_enabled(step)
+    }
+
+    /// Sets the optimization step that is being run.
+    pub fn begin_step(&mut self, step: Step) {
+        self.step = Some(step);
+    }
+
     /// Marks that a change was made to the program.
     pub fn mark_change(&mut self) {
         // Changes were made, so more optimization passes should be run.
         self.should_run_pass = true;
+        self.changes += 1;
+
+        if let Some(step) = self.step {
+            self.step_changes[step.index()] += 1;
+        }
     }
 
-    /// Returns whether a position in cells is in bounds of the playfield.
-    pub fn is_in_bounds(&self, x: usize, y: usize) -> bool {
-        let (width, height) = self.playfield.bounds();
-        x < width && y < height
+    /// Returns the number of changes made to the program.
+    pub fn changes(&self) -> usize {
+        self.changes
+    }
+
+    /// Returns the number of changes made to the program by each optimization
+    /// step.
+    pub fn step_changes(&self) -> Vec<(Step, usize)> {
+        Step::ALL.into_iter().zip(self.step_changes).collect()
+    }
+
+    /// Returns the bounds of the playfield in cells.
+    pub fn bounds(&self) -> (usize, usize) {
+        self.playfield.bounds()
+    }
+
+    /// Returns whether the playfield's standard wraps get and put coordinates.
+    pub fn wraps_coordinates(&self) -> bool {
+        self.playfield.standard().wraps_coordinates()
+    }
+
+    /// Returns the position in cells for the coordinates of a get or put.
+    /// Returns `None` if the coordinates are out of bounds.
+    pub fn position(&self, x: Value, y: Value) -> Option<(usize, usize)> {
+        self.playfield.position(x.into_i32(), y.into_i32())
+    }
+
+    /// Returns the value of the cell at a position in the playfield.
+    pub fn cell(&self, x: usize, y: usize) -> Value {
+        self.playfield
+            .get(x, y)
+            .expect("position should be in bounds")
     }
 
     /// Returns whether a target position in cells is reachable from a source
@@ -50,4 +134,23 @@ impl<'a> Context<'a> {
         self.flow_graph
             .is_reachable(source.position(), (target_x, target_y))
     }
+
+    /// Returns the strictest mode that a position in cells is reached in.
+    pub fn mode_at(&self, x: usize, y: usize) -> Mode {
+        self.flow_graph.mode_at((x, y))
+    }
+
+    /// Returns whether any position in an area of cells is reachable from a
+    /// source state.
+    pub fn is_area_reachable(
+        &self,
+        source: &State,
+        xs: &RangeInclusive<usize>,
+        ys: &RangeInclusive<usize>,
+    ) -> bool {
+        self.flow_graph
+            .is_any_reachable(source.position(), |(x, y)| {
+                xs.contains(&x) && ys.contains(&y)
+            })
+    }
 }
@@ -12,6 +12,11 @@ impl<'a> Graph<'a> {
         Self { program }
     }
 
+    /// Returns a reference to the inner program.
+    pub fn program(&self) -> &Program {
+        self.program
+    }
+
     /// Returns an iterator over a label's exit labels.
     pub fn exit_labels(&self, label: &Label) -> impl Iterator<Item = &Label> {
         self.block(label).exit.to_labels().into_iter()
@@ -1,28 +1,115 @@
 mod context;
 mod graph;
+mod options;
+mod range;
+mod stats;
 mod step;
 
+pub use options::{Level, Options};
+pub use stats::Stats;
+pub use step::Step;
+
 use context::Context;
 use graph::Graph;
 
 use crate::{common::Playfield, ir::Program, parse::FlowGraph};
 
-/// Optimizes a program with a flow graph and a playfield.
-pub fn optimize_program(program: &mut Program, flow_graph: &FlowGraph, playfield: &Playfield) {
+/// Optimizes a program with a flow graph and a playfield and returns statistics
+/// about the optimization.
+pub fn optimize_program(
+    program: &mut Program,
+    flow_graph: &FlowGraph,
+    playfield: &Playfield,
+) -> Stats {
+    optimize_program_observed(
+        program,
+        flow_graph,
+        playfield,
+        &Options::default(),
+        |_, _| {},
+    )
+}
+
+/// Optimizes a program with a flow graph, a playfield, and options and returns
+/// statistics about the optimization. An observer is called with the program
+/// after each optimization step.
+pub fn optimize_program_observed(
+    program: &mut Program,
+    flow_graph: &FlowGraph,
+    playfield: &Playfield,
+    options: &Options,
+    mut observer: impl FnMut(&StepRun, &Program),
+) -> Stats {
+    let (blocks_before, instructions_before) = stats::program_size(program);
     let mut graph = Graph::new(program);
-    let mut ctx = Context::new(flow_graph, playfield);
+    let mut ctx = Context::new(flow_graph, playfield, options);
 
     while ctx.should_run_pass() {
-        run_pass(&mut graph, &mut ctx);
+        run_pass(&mut graph, &mut ctx, &mut observer);
+    }
+
+    let (blocks_after, instructions_after) = stats::program_size(program);
+    let (dynamic_puts, lowered_puts, data_puts, guarded_puts) = stats::count_puts(program);
+    Stats {
+        blocks_before,
+        instructions_before,
+        blocks_after,
+        instructions_after,
+        passes: ctx.passes(),
+        step_changes: ctx.step_changes(),
+        dynamic_puts,
+        lowered_puts,
+        data_puts,
+        guarded_puts,
     }
 }
 
+/// A record of an optimization step that was run.
+#[derive(Clone, Copy)]
+pub struct StepRun {
+    /// The pass number, starting from 1.
+    pub pass: usize,
+
+    /// The optimization step.
+    pub step: Step,
+
+    /// Whether the step made any changes to the program.
+    pub changed: bool,
+}
+
 /// Runs an optimization pass.
-fn run_pass(graph: &mut Graph, ctx: &mut Context) {
-    step::merge_blocks(graph, ctx);
-    step::thread_jumps(graph, ctx);
-    step::remove_unreachable_blocks(graph, ctx);
-    step::replace_instructions(graph, ctx);
-    step::replace_jumps_to_exits(graph, ctx);
-    step::optimize_branches(graph, ctx);
+fn run_pass(graph: &mut Graph, ctx: &mut Context, observer: &mut impl FnMut(&StepRun, &Program)) {
+    let pass = ctx.passes();
+    for step in Step::ALL {
+        if !ctx.is_step_enabled(step) {
+            continue;
+        }
+
+        let changes = ctx.changes();
+        ctx.begin_step(step);
+        run_step(step, graph, ctx);
+
+        let changed = ctx.changes() != changes;
+        observer(
+            &StepRun {
+                pass,
+                step,
+                changed,
+            },
+            graph.program(),
+        );
+    }
+}
+
+/// Runs an optimization step.
+fn run_step(step: Step, graph: &mut Graph, ctx: &mut Context) {
+    match step {
+        Step::MergeBlocks => step::merge_blocks(graph, ctx),
+        Step::ThreadJumps => step::thread_jumps(graph, ctx),
+        Step::RemoveUnreachableBlocks => step::remove_unreachable_blocks(graph, ctx),
+        Step::ReplaceInstructions => step::replace_instructions(graph, ctx),
+        Step::ReplaceJumpsToExits => step::replace_jumps_to_exits(graph, ctx),
+        Step::OptimizeBranches => step::optimize_branches(graph, ctx),
+        Step::AnalyzeRanges => step::analyze_ranges(graph, ctx),
+    }
 }
@@ -0,0 +1,32 @@
+use super::Step;
+
+/// Options for optimizing a program.
+#[derive(Clone, Default)]
+pub struct Options {
+    /// The optimization level.
+    pub level: Level,
+
+    /// The optimization steps that should not be run.
+    pub disabled_steps: Vec<Step>,
+}
+
+impl Options {
+    /// Returns whether an optimization step is enabled.
+    pub fn is_step_enabled(&self, step: Step) -> bool {
+        self.level != Level::None && !self.disabled_steps.contains(&step)
+    }
+}
+
+/// An optimization level.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum Level {
+    /// No optimization. The program is left as it was parsed.
+    None,
+
+    /// A single optimization pass.
+    Once,
+
+    /// Optimization passes until no more changes can be made.
+    #[default]
+    Full,
+}
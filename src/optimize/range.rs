@@ -0,0 +1,241 @@
+use crate::ir::{
+    Instruction,
+    ops::{BinOp, UnOp},
+};
+
+/// The maximum number of values tracked individually at the top of a range
+/// stack. Deeper values are merged into the range of the rest of the stack.
+const MAX_DEPTH: usize = 16;
+
+/// An inclusive range of possible values.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    /// The minimum value.
+    pub min: i32,
+
+    /// The maximum value.
+    pub max: i32,
+}
+
+impl Range {
+    /// A range containing every value.
+    pub const FULL: Self = Self {
+        min: i32::MIN,
+        max: i32::MAX,
+    };
+
+    /// Creates a new range containing a single value.
+    pub fn exact(value: i32) -> Self {
+        Self {
+            min: value,
+            max: value,
+        }
+    }
+
+    /// Creates a new range from unwrapped bounds. Returns a full range if the
+    /// bounds do not fit in an `i32`, since the values may have wrapped.
+    fn from_wide(min: i64, max: i64) -> Self {
+        match (i32::try_from(min), i32::try_from(max)) {
+            (Ok(min), Ok(max)) => Self { min, max },
+            _ => Self::FULL,
+        }
+    }
+
+    /// Returns whether the range contains a value.
+    fn contains(self, value: i32) -> bool {
+        self.min <= value && value <= self.max
+    }
+
+    /// Returns the smallest range containing both ranges.
+    fn union(self, other: Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Returns the range widened to a larger range. Bounds that grew are moved
+    /// to the limits of an `i32` so that repeated widening terminates.
+    fn widen(self, larger: Self) -> Self {
+        Self {
+            min: if larger.min < self.min {
+                i32::MIN
+            } else {
+                self.min
+            },
+            max: if larger.max > self.max {
+                i32::MAX
+            } else {
+                self.max
+            },
+        }
+    }
+
+    /// Evaluates a unary operator over a range.
+    fn unary(op: UnOp, rhs: Self) -> Self {
+        match op {
+            UnOp::Not if !rhs.contains(0) => Self::exact(0),
+            UnOp::Not if rhs == Self::exact(0) => Self::exact(1),
+            UnOp::Not => Self { min: 0, max: 1 },
+        }
+    }
+
+    /// Evaluates a binary operator over ranges.
+    fn binary(op: BinOp, lhs: Self, rhs: Self) -> Self {
+        let (l_min, l_max) = (i64::from(lhs.min), i64::from(lhs.max));
+        let (r_min, r_max) = (i64::from(rhs.min), i64::from(rhs.max));
+        match op {
+            BinOp::Add => Self::from_wide(l_min + r_min, l_max + r_max),
+            BinOp::Subtract => Self::from_wide(l_min - r_max, l_max - r_min),
+            BinOp::Multiply => {
+                let corners = [l_min * r_min, l_min * r_max, l_max * r_min, l_max * r_max];
+                Self::from_wide(
+                    corners.into_iter().min().unwrap_or_default(),
+                    corners.into_iter().max().unwrap_or_default(),
+                )
+            }
+            BinOp::Greater if lhs.min > rhs.max => Self::exact(1),
+            BinOp::Greater if lhs.max <= rhs.min => Self::exact(0),
+            BinOp::Greater => Self { min: 0, max: 1 },
+            BinOp::Divide | BinOp::Modulo if rhs.contains(0) => Self::FULL,
+            BinOp::Divide => {
+                // Truncating division is monotonic in each operand when the
+                // divisor's sign is fixed, so the corners bound the result.
+                let corners = [l_min / r_min, l_min / r_max, l_max / r_min, l_max / r_max];
+                Self::from_wide(
+                    corners.into_iter().min().unwrap_or_default(),
+                    corners.into_iter().max().unwrap_or_default(),
+                )
+            }
+            BinOp::Modulo => {
+                // The remainder has the sign of the dividend and is smaller in
+                // magnitude than the divisor.
+                let bound = r_min.abs().max(r_max.abs()) - 1;
+                Self::from_wide(l_min.max(-bound).min(0), l_max.min(bound).max(0))
+            }
+        }
+    }
+}
+
+/// An abstract stack of value ranges.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RangeStack {
+    /// The ranges of the values at the top of the stack, from bottom to top.
+    values: Vec<Range>,
+
+    /// The range of every value below the tracked values, including the zeros
+    /// popped from an empty stack.
+    rest: Range,
+}
+
+impl RangeStack {
+    /// Creates a new range stack where every value is unknown.
+    pub fn unknown() -> Self {
+        Self {
+            values: Vec::new(),
+            rest: Range::FULL,
+        }
+    }
+
+    /// Returns the range of a value by its depth from the top of the stack.
+    pub fn peek(&self, depth: usize) -> Range {
+        self.values
+            .len()
+            .checked_sub(depth + 1)
+            .map_or(self.rest, |i| self.values[i])
+    }
+
+    /// Pushes a range to the stack.
+    pub fn push(&mut self, range: Range) {
+        self.values.push(range);
+        if self.values.len() > MAX_DEPTH {
+            self.rest = self.rest.union(self.values.remove(0));
+        }
+    }
+
+    /// Pops a range from the stack.
+    pub fn pop(&mut self) -> Range {
+        self.values.pop().unwrap_or(self.rest)
+    }
+
+    /// Applies an instruction's stack effect to the stack.
+    pub fn apply(&mut self, instruction: &Instruction) {
+        match instruction {
+            Instruction::Push(v) => self.push(Range::exact(v.into_i32())),
+            Instruction::Unary(o) => {
+                let rhs = self.pop();
+                self.push(Range::unary(*o, rhs));
+            }
+            Instruction::Binary(o) => {
+                let rhs = self.pop();
+                let lhs = self.pop();
+                self.push(Range::binary(*o, lhs, rhs));
+            }
+            Instruction::Divide(o) => {
+                // Dividing by zero pushes an integer from user input instead.
+                let rhs = self.pop();
+                let lhs = self.pop();
+                self.push(if rhs.contains(0) {
+                    Range::FULL
+                } else {
+                    Range::binary((*o).into(), lhs, rhs)
+                });
+            }
+            Instruction::Duplicate => {
+                let value = self.pop();
+                self.push(value);
+                self.push(value);
+            }
+            Instruction::Swap => {
+                let top = self.pop();
+                let under = self.pop();
+                self.push(top);
+                self.push(under);
+            }
+            Instruction::Pop | Instruction::OutputInt | Instruction::OutputChar => {
+                self.pop();
+            }
+            Instruction::Get => {
+                self.pop();
+                self.pop();
+                self.push(Range::FULL);
+            }
+            Instruction::Put(_) | Instruction::PutData => {
+                self.pop();
+                self.pop();
+                self.pop();
+            }
+            Instruction::InputInt | Instruction::GetAt(_, _) => self.push(Range::FULL),
+            Instruction::InputChar => self.push(Range {
+                min: -1,
+                max: char::MAX as i32,
+            }),
+            Instruction::Print(_) => {}
+            Instruction::PutAt(_, _) | Instruction::PutAtGuarded(_, _, _, _) => {
+                self.pop();
+            }
+        }
+    }
+
+    /// Returns the smallest range stack containing both range stacks.
+    pub fn union(&self, other: &Self) -> Self {
+        self.zip_with(other, Range::union)
+    }
+
+    /// Returns the range stack widened to a larger range stack.
+    pub fn widen(&self, larger: &Self) -> Self {
+        self.zip_with(larger, Range::widen)
+    }
+
+    /// Combines the ranges of two range stacks that are at the same depth.
+    fn zip_with(&self, other: &Self, f: impl Fn(Range, Range) -> Range) -> Self {
+        let depth = self.values.len().max(other.values.len());
+        Self {
+            values: (0..depth)
+                .rev()
+                .map(|d| f(self.peek(d), other.peek(d)))
+                .collect(),
+            rest: f(self.rest, other.rest),
+        }
+    }
+}
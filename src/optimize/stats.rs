@@ -0,0 +1,92 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::ir::{Instruction, Program};
+
+use super::Step;
+
+/// Statistics about optimizing a program.
+#[derive(Clone, Default)]
+pub struct Stats {
+    /// The number of blocks before optimization.
+    pub blocks_before: usize,
+
+    /// The number of instructions before optimization.
+    pub instructions_before: usize,
+
+    /// The number of blocks after optimization.
+    pub blocks_after: usize,
+
+    /// The number of instructions after optimization.
+    pub instructions_after: usize,
+
+    /// The number of optimization passes that were run.
+    pub passes: usize,
+
+    /// The number of changes made by each optimization step, in the order the
+    /// steps are run in a pass.
+    pub step_changes: Vec<(Step, usize)>,
+
+    /// The number of put instructions that stayed dynamic after optimization.
+    pub dynamic_puts: usize,
+
+    /// The number of put instructions that were lowered to known coordinates.
+    pub lowered_puts: usize,
+
+    /// The number of put instructions that were proven to never modify the
+    /// program.
+    pub data_puts: usize,
+
+    /// The number of put instructions that were lowered to known coordinates
+    /// with a guard against modifying the program.
+    pub guarded_puts: usize,
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let blocks = format!("{} -> {}", self.blocks_before, self.blocks_after);
+        let instructions = format!(
+            "{} -> {}",
+            self.instructions_before, self.instructions_after
+        );
+
+        writeln!(f, "optimization statistics:")?;
+        writeln!(f, "    {:<30}{blocks}", "blocks")?;
+        writeln!(f, "    {:<30}{instructions}", "instructions")?;
+        writeln!(f, "    {:<30}{}", "passes", self.passes)?;
+        writeln!(f, "    {:<30}{}", "dynamic puts", self.dynamic_puts)?;
+        writeln!(f, "    {:<30}{}", "lowered puts", self.lowered_puts)?;
+        writeln!(f, "    {:<30}{}", "data puts", self.data_puts)?;
+        writeln!(f, "    {:<30}{}", "guarded puts", self.guarded_puts)?;
+        write!(f, "    changes per step:")?;
+
+        for (step, changes) in &self.step_changes {
+            write!(f, "\n        {:<26}{changes}", step.to_string())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the number of blocks and instructions in a program.
+pub fn program_size(program: &Program) -> (usize, usize) {
+    let instructions = program.blocks.values().map(|b| b.instructions.len()).sum();
+    (program.blocks.len(), instructions)
+}
+
+/// Returns the number of dynamic put instructions, put instructions with known
+/// coordinates, data put instructions, and guarded put instructions in a
+/// program.
+pub fn count_puts(program: &Program) -> (usize, usize, usize, usize) {
+    let (mut dynamic_puts, mut lowered_puts, mut data_puts, mut guarded_puts) = (0, 0, 0, 0);
+    for instruction in program.blocks.values().flat_map(|b| &b.instructions) {
+        match instruction {
+            Instruction::Put(_) => dynamic_puts += 1,
+            Instruction::PutAt(_, _) => lowered_puts += 1,
+            Instruction::PutData => data_puts += 1,
+            Instruction::PutAtGuarded(_, _, _, _) => guarded_puts += 1,
+            _ => {}
+        }
+    }
+
+    (dynamic_puts, lowered_puts, data_puts, guarded_puts)
+}
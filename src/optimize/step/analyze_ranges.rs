@@ -0,0 +1,140 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::RangeInclusive,
+};
+
+use crate::{
+    common::Value,
+    ir::{Exit, Instruction, Label},
+    optimize::{
+        context::Context,
+        graph::Graph,
+        range::{Range, RangeStack},
+    },
+};
+
+/// The number of times a block's entry stack may grow before it is widened.
+const WIDENING_DELAY: usize = 4;
+
+/// Analyzes the ranges of stack values across blocks to lower get and put
+/// instructions with dynamic coordinates. Gets and puts whose coordinates are
+/// always out of bounds are removed, and puts that can never modify a reachable
+/// cell are lowered to data puts.
+pub fn analyze_ranges(graph: &mut Graph, ctx: &mut Context) {
+    let entry_stacks = entry_stacks(graph);
+    for label in graph.labels_cloned() {
+        let mut stack = entry_stacks
+            .get(&label)
+            .cloned()
+            .unwrap_or_else(RangeStack::unknown);
+
+        let block = graph.block_mut(&label);
+        let mut instructions = Vec::with_capacity(block.instructions.len());
+        for instruction in &block.instructions {
+            let (x, y) = (stack.peek(1), stack.peek(0));
+            let (width, height) = ctx.bounds();
+            let area = if ctx.wraps_coordinates() {
+                Some((wrapped(x, width), wrapped(y, height)))
+            } else {
+                in_bounds(x, width).zip(in_bounds(y, height))
+            };
+            stack.apply(instruction);
+
+            match (instruction, &area) {
+                (Instruction::Get, None) => {
+                    instructions.extend([
+                        Instruction::Pop,
+                        Instruction::Pop,
+                        Instruction::Push(Value::default()),
+                    ]);
+                    ctx.mark_change();
+                }
+                (Instruction::Put(_) | Instruction::PutData, None) => {
+                    instructions.extend([Instruction::Pop, Instruction::Pop, Instruction::Pop]);
+                    ctx.mark_change();
+                }
+                (Instruction::Put(s), Some((xs, ys))) if !ctx.is_area_reachable(s, xs, ys) => {
+                    instructions.push(Instruction::PutData);
+                    ctx.mark_change();
+                }
+                _ => instructions.push(instruction.clone()),
+            }
+        }
+
+        block.instructions = instructions;
+    }
+}
+
+/// Returns the stacks at the entries of blocks that are reachable from the
+/// main entry point. The stack at the main entry point is unknown, since
+/// recompiled programs start with the stack of the previous program.
+fn entry_stacks(graph: &Graph) -> BTreeMap<Label, RangeStack> {
+    let mut entry_stacks = BTreeMap::new();
+    let mut growths = BTreeMap::new();
+    let mut pending_labels = BTreeSet::new();
+    entry_stacks.insert(Label::Main, RangeStack::unknown());
+    pending_labels.insert(Label::Main);
+
+    while let Some(label) = pending_labels.pop_first() {
+        let block = graph.block(&label);
+        let mut stack = entry_stacks[&label].clone();
+        for instruction in &block.instructions {
+            stack.apply(instruction);
+        }
+
+        if let Exit::Branch(_, _) = block.exit {
+            stack.pop();
+        }
+
+        for successor in graph.exit_labels(&label) {
+            let entry_stack = match entry_stacks.get(successor) {
+                None => stack.clone(),
+                Some(old_stack) => {
+                    let new_stack = old_stack.union(&stack);
+                    if new_stack == *old_stack {
+                        continue;
+                    }
+
+                    // Loops may grow ranges forever, so entry stacks that keep
+                    // growing are widened.
+                    let growth = growths.entry(successor).or_insert(0);
+                    *growth += 1;
+                    if *growth > WIDENING_DELAY {
+                        old_stack.widen(&new_stack)
+                    } else {
+                        new_stack
+                    }
+                }
+            };
+
+            entry_stacks.insert(successor.clone(), entry_stack);
+            pending_labels.insert(successor.clone());
+        }
+    }
+
+    entry_stacks
+}
+
+/// Returns the in-bounds part of a range of coordinates along an axis with a
+/// size. Returns `None` if every coordinate is out of bounds.
+fn in_bounds(range: Range, size: usize) -> Option<RangeInclusive<usize>> {
+    let min = usize::try_from(range.min.max(0)).ok()?;
+    let max = usize::try_from(range.max).ok()?.min(size.checked_sub(1)?);
+    (min <= max).then_some(min..=max)
+}
+
+/// Returns the smallest range containing a range of coordinates after wrapping
+/// them around an axis with a size.
+fn wrapped(range: Range, size: usize) -> RangeInclusive<usize> {
+    let size = i64::try_from(size).expect("playfield size should fit in an `i64`");
+    let (min, max) = (i64::from(range.min), i64::from(range.max));
+    let (wrapped_min, wrapped_max) = (min.rem_euclid(size), max.rem_euclid(size));
+    let (min, max) = if max - min < size && wrapped_min <= wrapped_max {
+        (wrapped_min, wrapped_max)
+    } else {
+        (0, size - 1)
+    };
+
+    let to_usize = |c| usize::try_from(c).expect("wrapped coordinate should not be negative");
+    to_usize(min)..=to_usize(max)
+}
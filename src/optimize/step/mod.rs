@@ -1,3 +1,6 @@
+use std::fmt::{self, Display, Formatter};
+
+mod analyze_ranges;
 mod merge_blocks;
 mod optimize_branches;
 mod remove_unreachable_blocks;
@@ -5,9 +8,73 @@ mod replace_instructions;
 mod replace_jumps_to_exits;
 mod thread_jumps;
 
+pub use analyze_ranges::analyze_ranges;
 pub use merge_blocks::merge_blocks;
 pub use optimize_branches::optimize_branches;
 pub use remove_unreachable_blocks::remove_unreachable_blocks;
 pub use replace_instructions::replace_instructions;
 pub use replace_jumps_to_exits::replace_jumps_to_exits;
 pub use thread_jumps::thread_jumps;
+
+/// An optimization step.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// A step to merge blocks into their predecessor.
+    MergeBlocks,
+
+    /// A step to redirect labels targeting unconditional jumps.
+    ThreadJumps,
+
+    /// A step to remove unreachable blocks.
+    RemoveUnreachableBlocks,
+
+    /// A step to perform peephole optimization.
+    ReplaceInstructions,
+
+    /// A step to replace jumps to exits with the exit.
+    ReplaceJumpsToExits,
+
+    /// A step to optimize branch exits.
+    OptimizeBranches,
+
+    /// A step to lower gets and puts by analyzing the ranges of their
+    /// coordinates.
+    AnalyzeRanges,
+}
+
+impl Step {
+    /// The optimization steps in the order they are run in a pass.
+    pub const ALL: [Self; 7] = [
+        Self::MergeBlocks,
+        Self::ThreadJumps,
+        Self::RemoveUnreachableBlocks,
+        Self::ReplaceInstructions,
+        Self::ReplaceJumpsToExits,
+        Self::OptimizeBranches,
+        Self::AnalyzeRanges,
+    ];
+
+    /// Returns the index of the step in `Step::ALL`.
+    pub fn index(self) -> usize {
+        Self::ALL
+            .iter()
+            .position(|s| *s == self)
+            .expect("every step should be in `Step::ALL`")
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let data = match self {
+            Self::MergeBlocks => "merge-blocks",
+            Self::ThreadJumps => "thread-jumps",
+            Self::RemoveUnreachableBlocks => "remove-unreachable-blocks",
+            Self::ReplaceInstructions => "replace-instructions",
+            Self::ReplaceJumpsToExits => "replace-jumps-to-exits",
+            Self::OptimizeBranches => "optimize-branches",
+            Self::AnalyzeRanges => "analyze-ranges",
+        };
+
+        f.write_str(data)
+    }
+}
@@ -1,18 +1,59 @@
+use std::collections::BTreeSet;
+
 use crate::{
     common::Value,
-    ir::Instruction,
+    ir::{Instruction, Program},
     optimize::{context::Context, graph::Graph},
 };
 
 /// Performs peephole optimization to replace instructions with more optimal
 /// equivalents.
 pub fn replace_instructions(graph: &mut Graph, ctx: &mut Context) {
+    let written_cells = written_cells(graph.program());
     for block in graph.blocks_mut() {
+        if let Some(written_cells) = &written_cells {
+            fold_cells(&mut block.instructions, written_cells, ctx);
+        }
+
         optimize_peepholes(&mut block.instructions, 3, ctx);
         optimize_peepholes(&mut block.instructions, 2, ctx);
     }
 }
 
+/// Returns the positions of the cells that a program may write to. Returns
+/// `None` if the program has put instructions with dynamic positions, which
+/// may write to any cell.
+fn written_cells(program: &Program) -> Option<BTreeSet<(usize, usize)>> {
+    let mut cells = BTreeSet::new();
+    for instruction in program.blocks.values().flat_map(|b| &b.instructions) {
+        match instruction {
+            Instruction::Put(_) | Instruction::PutData => return None,
+            Instruction::PutAt(x, y) | Instruction::PutAtGuarded(x, y, _, _) => {
+                cells.insert((*x, *y));
+            }
+            _ => {}
+        }
+    }
+
+    Some(cells)
+}
+
+/// Replaces loads of cells that are never written with pushes of their values.
+fn fold_cells(
+    instructions: &mut [Instruction],
+    written_cells: &BTreeSet<(usize, usize)>,
+    ctx: &mut Context,
+) {
+    for instruction in instructions {
+        if let Instruction::GetAt(x, y) = *instruction
+            && !written_cells.contains(&(x, y))
+        {
+            *instruction = Instruction::Push(ctx.cell(x, y));
+            ctx.mark_change();
+        }
+    }
+}
+
 /// Performs peephole optimization on a vector of instructions with a window
 /// size and returns whether any changes were made.
 fn optimize_peepholes(instructions: &mut Vec<Instruction>, window_size: usize, ctx: &mut Context) {
@@ -41,27 +82,29 @@ fn optimize_peepholes(instructions: &mut Vec<Instruction>, window_size: usize, c
 fn optimize_peephole(peephole: &[Instruction], ctx: &Context) -> Option<Vec<Instruction>> {
     use Instruction::{
         Binary, Divide, Duplicate, Get, GetAt, OutputChar, OutputInt, Pop, Print, Push, Put, PutAt,
-        Swap, Unary,
+        PutAtGuarded, PutData, Swap, Unary,
     };
 
     let peephole = match peephole {
         [Push(x), Push(y), Get] => {
-            if let (Ok(x), Ok(y)) = (usize::try_from(x.into_i32()), usize::try_from(y.into_i32())) {
-                if ctx.is_in_bounds(x, y) {
-                    return Some(vec![GetAt(x, y)]);
-                }
+            if let Some((x, y)) = ctx.position(*x, *y) {
+                return Some(vec![GetAt(x, y)]);
             }
             vec![Push(Value::default())]
         }
         [Push(x), Push(y), Put(s)] => {
-            if let (Ok(x), Ok(y)) = (usize::try_from(x.into_i32()), usize::try_from(y.into_i32())) {
-                if ctx.is_in_bounds(x, y) {
-                    return if ctx.is_reachable(s, x, y) {
-                        None
-                    } else {
-                        Some(vec![PutAt(x, y)])
-                    };
-                }
+            if let Some((x, y)) = ctx.position(*x, *y) {
+                return Some(vec![if ctx.is_reachable(s, x, y) {
+                    PutAtGuarded(x, y, ctx.mode_at(x, y), s.clone())
+                } else {
+                    PutAt(x, y)
+                }]);
+            }
+            vec![Pop]
+        }
+        [Push(x), Push(y), PutData] => {
+            if let Some((x, y)) = ctx.position(*x, *y) {
+                return Some(vec![PutAt(x, y)]);
             }
             vec![Pop]
         }
@@ -30,6 +30,11 @@ impl<'a> Cursor<'a> {
         self.state.mode
     }
 
+    /// Returns the position of the cursor.
+    pub fn position(&self) -> (usize, usize) {
+        self.state.position()
+    }
+
     /// Returns the value under the cursor.
     pub fn value(&self) -> Value {
         self.playfield
@@ -1,36 +1,90 @@
 use std::collections::{BTreeSet, HashMap, HashSet};
 
+use crate::ir::{Exit, Label, Program, State, state::Mode};
+
 /// A directed graph of reachable positions in a program.
 pub struct FlowGraph {
     /// The reachable positions and the positions they flow into.
     connections: HashMap<(usize, usize), HashSet<(usize, usize)>>,
+
+    /// The reachable positions that are reached in command mode.
+    command_positions: HashSet<(usize, usize)>,
 }
 
 impl FlowGraph {
-    /// Creates a new flow graph from a root position.
-    pub fn new(root: (usize, usize)) -> Self {
-        let mut connections = HashMap::new();
-        connections.insert(root, HashSet::new());
-        Self { connections }
+    /// Creates a new flow graph from a root state.
+    pub fn new(root: &State) -> Self {
+        let mut flow_graph = Self {
+            connections: HashMap::new(),
+            command_positions: HashSet::new(),
+        };
+
+        flow_graph.insert_state(root);
+        flow_graph
     }
 
-    /// Inserts a new connection between a source position and a target
-    /// position.
-    pub fn insert_connection(&mut self, source: (usize, usize), target: (usize, usize)) {
+    /// Creates a new flow graph from the blocks of a parsed program that are
+    /// reachable from its main entry point.
+    pub fn from_program(program: &Program) -> Self {
+        let Exit::Jump(Label::State(main_state)) = &program.blocks[&Label::Main].exit else {
+            panic!("main block should jump to a state");
+        };
+
+        let mut flow_graph = Self::new(main_state);
+        let mut pending_states = BTreeSet::new();
+        let mut checked_states = BTreeSet::new();
+        pending_states.insert(main_state);
+
+        while let Some(state) = pending_states.pop_first() {
+            if !checked_states.insert(state) {
+                continue;
+            }
+
+            let block = &program.blocks[&Label::State(state.clone())];
+            for target_state in block.exit.states() {
+                flow_graph.insert_connection(state, target_state);
+                pending_states.insert(target_state);
+            }
+        }
+
+        flow_graph
+    }
+
+    /// Inserts a new connection between the positions of a source state and a
+    /// target state.
+    pub fn insert_connection(&mut self, source: &State, target: &State) {
         self.connections
-            .get_mut(&source)
+            .get_mut(&source.position())
             .expect("source position should exist in flow map")
-            .insert(target);
-        self.connections.entry(target).or_default();
+            .insert(target.position());
+        self.insert_state(target);
+    }
+
+    /// Returns the strictest mode that a position is reached in. Command mode
+    /// is stricter than string mode, since a quote is also a command, so any
+    /// change that changes the program in string mode changes it in command
+    /// mode.
+    pub fn mode_at(&self, position: (usize, usize)) -> Mode {
+        if self.command_positions.contains(&position) {
+            Mode::Command
+        } else {
+            Mode::String
+        }
     }
 
     /// Returns whether a target position is reachable from a source position.
     pub fn is_reachable(&self, source: (usize, usize), target: (usize, usize)) -> bool {
-        assert!(self.connections.contains_key(&source));
-        if !self.connections.contains_key(&target) {
-            return false;
-        }
+        self.connections.contains_key(&target) && self.is_any_reachable(source, |p| p == target)
+    }
 
+    /// Returns whether any position matching a predicate is reachable from a
+    /// source position.
+    pub fn is_any_reachable(
+        &self,
+        source: (usize, usize),
+        mut predicate: impl FnMut((usize, usize)) -> bool,
+    ) -> bool {
+        assert!(self.connections.contains_key(&source));
         let mut pending_positions = BTreeSet::new();
         let mut checked_positions = HashSet::new();
         pending_positions.insert(source);
@@ -39,7 +93,7 @@ impl FlowGraph {
             if checked_positions.contains(&position) {
                 continue;
             }
-            if position == target {
+            if predicate(position) {
                 return true;
             }
 
@@ -49,4 +103,12 @@ impl FlowGraph {
 
         false
     }
+
+    /// Inserts the position of a reached state.
+    fn insert_state(&mut self, state: &State) {
+        self.connections.entry(state.position()).or_default();
+        if state.mode == Mode::Command {
+            self.command_positions.insert(state.position());
+        }
+    }
 }
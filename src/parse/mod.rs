@@ -8,7 +8,7 @@ use std::collections::{BTreeMap, BTreeSet};
 use cursor::Cursor;
 
 use crate::{
-    common::Playfield,
+    common::{Playfield, Value},
     ir::{
         Block, Exit, Instruction, Label, Program, State,
         ops::{BinOp, DivOp, UnOp},
@@ -16,6 +16,10 @@ use crate::{
     },
 };
 
+/// The characters that are parsed as commands in command mode. This must match
+/// the commands that are handled by `parse_block`.
+const COMMANDS: &str = "0123456789+-*/%!`><^v?_|\":\\$.,#gp&~@";
+
 /// Parses a program and a flow graph from a playfield.
 pub fn parse_program(playfield: &Playfield) -> (Program, FlowGraph) {
     parse_program_state(playfield, State::default())
@@ -31,29 +35,134 @@ pub fn parse_program_state(playfield: &Playfield, main_state: State) -> (Program
         Exit::Jump(Label::State(main_state.clone())).into_block(),
     );
 
-    let mut flow_graph = FlowGraph::new(main_state.position());
+    let mut flow_graph = FlowGraph::new(&main_state);
+    let mut unexplored_states = BTreeSet::new();
+    unexplored_states.insert(main_state);
+    parse_states(&mut program, playfield, unexplored_states, |from, to| {
+        flow_graph.insert_connection(from, to);
+    });
+
+    (program, flow_graph)
+}
+
+/// Reparses a program after the cells at positions in a playfield changed,
+/// with a new main state. Only the blocks parsed from the changed cells are
+/// parsed again, along with any states that become reachable from them. Blocks
+/// that are no longer reachable are kept, because later changes may make them
+/// reachable again.
+///
+/// The program must not be optimized, so that each block is parsed from the
+/// cell at its state's position. The positions must include every cell that
+/// changed since the program was parsed or last reparsed, however it was
+/// written, or blocks parsed from the old values of those cells are kept.
+pub fn reparse_program(
+    program: &mut Program,
+    playfield: &Playfield,
+    positions: impl IntoIterator<Item = (usize, usize)>,
+    main_state: State,
+) {
+    program.blocks.insert(
+        Label::Main,
+        Exit::Jump(Label::State(main_state.clone())).into_block(),
+    );
+
     let mut unexplored_states = BTreeSet::new();
+    for position in positions {
+        let changed_states: Vec<_> = states_at(program, position).cloned().collect();
+        for state in changed_states {
+            program.blocks.remove(&Label::State(state.clone()));
+            unexplored_states.insert(state);
+        }
+    }
+
     unexplored_states.insert(main_state);
+    parse_states(program, playfield, unexplored_states, |_, _| {});
+}
+
+/// Returns whether changing the value of the cell at a position from a previous
+/// value would change how a parsed program is parsed. The value is compared in
+/// each mode that the program reaches the cell in, so changes to unreachable
+/// cells, changes between values that are both no-ops in command mode, and
+/// changes in string mode that don't add or remove a quote do not change the
+/// program.
+pub fn changes_program(
+    program: &Program,
+    position: (usize, usize),
+    previous_value: Value,
+    value: Value,
+) -> bool {
+    states_at(program, position).any(|s| changes_parse(s.mode, previous_value, value))
+}
 
+/// Returns whether changing the value of a cell from a previous value would
+/// change how the cell is parsed in a mode.
+pub fn changes_parse(mode: Mode, previous_value: Value, value: Value) -> bool {
+    previous_value != value
+        && match mode {
+            Mode::Command => is_command(previous_value) || is_command(value),
+            Mode::String => is_quote(previous_value) != is_quote(value),
+        }
+}
+
+/// Returns an iterator over the states of a program's blocks at a position.
+fn states_at(program: &Program, position: (usize, usize)) -> impl Iterator<Item = &State> {
+    let (x, y) = position;
+    let first_state = State {
+        y,
+        x,
+        mode: Mode::Command,
+        direction: Direction::Right,
+    };
+
+    let last_state = State {
+        y,
+        x,
+        mode: Mode::String,
+        direction: Direction::Up,
+    };
+
+    program
+        .blocks
+        .range(Label::State(first_state)..=Label::State(last_state))
+        .filter_map(|(l, _)| l.to_state())
+}
+
+/// Returns whether a value is parsed as a command in command mode. Values that
+/// are not commands are parsed as no-ops.
+fn is_command(value: Value) -> bool {
+    COMMANDS.contains(value.into_char_lossy())
+}
+
+/// Returns whether a value is parsed as a quote, which toggles string mode.
+fn is_quote(value: Value) -> bool {
+    value.into_char_lossy() == '"'
+}
+
+/// Parses blocks for a set of unexplored states and the states that they
+/// reach, skipping states that already have blocks in a program. Each
+/// connection between states is passed to a callback.
+fn parse_states(
+    program: &mut Program,
+    playfield: &Playfield,
+    mut unexplored_states: BTreeSet<State>,
+    mut connect: impl FnMut(&State, &State),
+) {
     while let Some(state) = unexplored_states.pop_first() {
         let label = Label::State(state.clone());
         if program.blocks.contains_key(&label) {
             continue;
         }
 
-        let position = state.position();
-        let cursor = Cursor::new(playfield, state);
+        let cursor = Cursor::new(playfield, state.clone());
         let block = parse_block(cursor);
 
         for unexplored_state in block.exit.states() {
-            flow_graph.insert_connection(position, unexplored_state.position());
+            connect(&state, unexplored_state);
             unexplored_states.insert(unexplored_state.clone());
         }
 
         program.blocks.insert(label, block);
     }
-
-    (program, flow_graph)
 }
 
 /// Parses a block from a cursor.
@@ -97,7 +206,7 @@ fn parse_block(cursor: Cursor) -> Block {
         (Mode::Command, '~') => Instruction::InputChar.into_block(cursor),
         (Mode::Command, '@') => Exit::End.into_block(),
         (Mode::Command, _) => cursor.step().into(),
-        (Mode::String, _) => push(value.into_i32(), cursor),
+        (Mode::String, _) => get_at(cursor),
     }
 }
 
@@ -106,6 +215,15 @@ fn push(value: i32, cursor: Cursor) -> Block {
     Instruction::Push(value.into()).into_block(cursor)
 }
 
+/// Creates a new block that pushes the value of the cell under a cursor. The
+/// value is loaded from the playfield instead of being pushed as a constant,
+/// so that the block stays the same when the cell changes to another value
+/// that is not a quote.
+fn get_at(cursor: Cursor) -> Block {
+    let (x, y) = cursor.position();
+    Instruction::GetAt(x, y).into_block(cursor)
+}
+
 /// Creates a new unary operation block from an operator and a cursor.
 fn unary(op: UnOp, cursor: Cursor) -> Block {
     Instruction::Unary(op).into_block(cursor)
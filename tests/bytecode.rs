@@ -0,0 +1,76 @@
+//! Tests for interpreting programs as bytecode.
+
+use fungus::{
+    Builder, Playfield, Value,
+    interpret::{BufferIo, SeededRandom},
+    optimize::{Level, Options},
+};
+
+/// Programs, their input, and their expected output.
+const PROGRAMS: [(&str, &str, &str); 7] = [
+    ("\"!dlroW ,olleH\">:#,_@", "", "Hello, World!"),
+    ("9>:.:#v_@\n ^  -1<", "", "9 8 7 6 5 4 3 2 1 0 "),
+    ("&&+.~,~,@", "3\n4\nab", "7 ab"),
+    (
+        "10/.93%.05-2/.05-2%.@",
+        "5\n",
+        "What do you want 1/0 to be? 5 0 -2 -1 ",
+    ),
+    ("\"a\"50p50g,@", "", "a"),
+    (
+        "0>:2%\"1\"+92p v\n\n |!`9:+1.    <\n @",
+        "",
+        "1 2 1 2 1 2 1 2 1 2 ",
+    ),
+    (
+        "\"a\"&2p0|\n       >\"7\"83p\"v\"&&pv\n       ^            <\n       >1.@",
+        "0\n7\n2\n",
+        "7 ",
+    ),
+];
+
+/// Runs source code at an optimization level with input and a seeded random
+/// source, and returns its output and final playfield cells.
+fn run(source: &str, input: &str, level: Level) -> (String, Vec<Option<i32>>) {
+    let mut io = BufferIo::new(input);
+    let playfield = Builder::new(source)
+        .options(Options {
+            level,
+            ..Options::default()
+        })
+        .compile()
+        .run_with(&mut io, &mut SeededRandom::new(7))
+        .expect("program should not exceed any limits");
+
+    (io.into_output(), cells(&playfield))
+}
+
+/// Returns the cells of a playfield in row-major order.
+fn cells(playfield: &Playfield) -> Vec<Option<i32>> {
+    let (width, height) = playfield.bounds();
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| playfield.get(x, y).map(Value::into_i32)))
+        .collect()
+}
+
+/// Bytecode produces the expected output for programs, including programs that
+/// modify themselves.
+#[test]
+fn runs_programs() {
+    for (source, input, expected) in PROGRAMS {
+        assert_eq!(run(source, input, Level::Full).0, expected, "{source}");
+    }
+}
+
+/// Bytecode for unoptimized and optimized programs produces the same output and
+/// final playfield.
+#[test]
+fn matches_unoptimized_programs() {
+    let random = "v>1.@\n>?2.@\n >3.@\n ^4.@";
+    for (source, input, _) in PROGRAMS.into_iter().chain([(random, "", "")]) {
+        let expected = run(source, input, Level::None);
+        for level in [Level::Once, Level::Full] {
+            assert_eq!(run(source, input, level), expected, "{source}");
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Tests for caching compiled programs.
+
+use std::{collections::BTreeMap, env, fs, path::PathBuf, process};
+
+use fungus::{
+    Block, Builder, Exit, Instruction, Label, Playfield, Program, Standard, State,
+    cache::{Cache, Error, Key},
+    interpret::{BufferIo, OsRandom},
+    optimize::{Options, Stats, Step},
+};
+
+/// A temporary cache directory.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates a new temporary cache directory with a name.
+    fn new(name: &str) -> Self {
+        Self(env::temp_dir().join(format!("fungus-cache-{name}-{}", process::id())))
+    }
+
+    /// Returns the path of the cache file for a key.
+    fn file(&self, key: &Key) -> PathBuf {
+        self.0.join(format!("{key}.fungus"))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Compiles and runs source code with a cache directory and returns its
+/// output.
+fn run(source: &str, dir: &TempDir) -> String {
+    let mut io = BufferIo::new("");
+    Builder::new(source)
+        .cache(Cache::new(&dir.0))
+        .compile()
+        .run_with(&mut io, &mut OsRandom)
+        .expect("program should not exceed any limits");
+    io.into_output()
+}
+
+/// Returns the compiled program and statistics for source code.
+fn compile(source: &str) -> (Program, Stats) {
+    let compiled = Builder::new(source).compile();
+    let stats = compiled.stats().clone();
+    (compiled.into_parts().0, stats)
+}
+
+/// Returns a program with a single main block.
+fn main_program(instructions: Vec<Instruction>, exit: Exit) -> Program {
+    Program {
+        blocks: BTreeMap::from([(Label::Main, Block { instructions, exit })]),
+    }
+}
+
+/// Compiled programs are stored and loaded for the same key.
+#[test]
+fn loads_stored_programs() {
+    let dir = TempDir::new("load");
+    assert_eq!(run("12+.@", &dir), "3 ");
+
+    let key = Key::new(
+        &Playfield::new("12+.@", Standard::default()),
+        &Options::default(),
+    );
+    assert!(dir.file(&key).exists());
+
+    let (program, _) = Cache::new(&dir.0)
+        .load(&key)
+        .expect("cached program should be valid")
+        .expect("program should be cached");
+    assert_eq!(program.to_string(), compile("12+.@").0.to_string());
+    assert_eq!(run("12+.@", &dir), "3 ");
+}
+
+/// Keys don't depend on the order or repetition of disabled steps.
+#[test]
+fn sorts_disabled_steps() {
+    let playfield = Playfield::new("12+.@", Standard::default());
+    let options = |disabled_steps| Options {
+        disabled_steps,
+        ..Options::default()
+    };
+
+    let key = Key::new(
+        &playfield,
+        &options(vec![Step::ThreadJumps, Step::MergeBlocks]),
+    );
+    let other_key = Key::new(
+        &playfield,
+        &options(vec![
+            Step::MergeBlocks,
+            Step::ThreadJumps,
+            Step::MergeBlocks,
+        ]),
+    );
+
+    assert!(key == other_key);
+    assert_eq!(key.to_string(), other_key.to_string());
+    assert!(key != Key::new(&playfield, &options(vec![Step::MergeBlocks])));
+}
+
+/// Cached programs for other playfields are rejected even if they are stored
+/// in the file for the key, and the program is compiled again.
+#[test]
+fn rejects_mismatched_programs() {
+    let dir = TempDir::new("mismatch");
+    let cache = Cache::new(&dir.0);
+    let key = Key::new(
+        &Playfield::new("12+.@", Standard::default()),
+        &Options::default(),
+    );
+    let other_key = Key::new(
+        &Playfield::new("34+.@", Standard::default()),
+        &Options::default(),
+    );
+    let (program, stats) = compile("34+.@");
+    cache
+        .store(&other_key, &program, &stats)
+        .expect("program should be stored");
+
+    fs::rename(dir.file(&other_key), dir.file(&key)).expect("cache file should be renamed");
+    assert!(matches!(cache.load(&key), Err(Error::KeyMismatch)));
+    assert_eq!(run("12+.@", &dir), "3 ");
+}
+
+/// Corrupt cache files are rejected, and the program is compiled again.
+#[test]
+fn rejects_corrupt_files() {
+    let dir = TempDir::new("corrupt");
+    let cache = Cache::new(&dir.0);
+    let key = Key::new(
+        &Playfield::new("12+.@", Standard::default()),
+        &Options::default(),
+    );
+    assert_eq!(run("12+.@", &dir), "3 ");
+
+    let bytes = fs::read(dir.file(&key)).expect("cache file should be read");
+    fs::write(dir.file(&key), &bytes[..bytes.len() - 1]).expect("cache file should be written");
+    assert!(matches!(cache.load(&key), Err(Error::Corrupt)));
+    assert_eq!(run("12+.@", &dir), "3 ");
+
+    fs::write(dir.file(&key), b"not a cached program").expect("cache file should be written");
+    assert!(matches!(cache.load(&key), Err(Error::InvalidHeader)));
+    assert_eq!(run("12+.@", &dir), "3 ");
+}
+
+/// Cached programs with positions or states outside of the playfield are
+/// rejected.
+#[test]
+fn rejects_positions_out_of_bounds() {
+    let dir = TempDir::new("bounds");
+    let cache = Cache::new(&dir.0);
+    let key = Key::new(
+        &Playfield::new("12+.@", Standard::default()),
+        &Options::default(),
+    );
+    let state = State {
+        x: 5,
+        ..State::default()
+    };
+
+    let programs = [
+        main_program(vec![Instruction::GetAt(5, 0)], Exit::End),
+        main_program(vec![Instruction::PutAt(0, 1)], Exit::End),
+        main_program(vec![Instruction::Put(state.clone())], Exit::End),
+        Program {
+            blocks: BTreeMap::from([
+                (
+                    Label::Main,
+                    Block {
+                        instructions: Vec::new(),
+                        exit: Exit::Jump(Label::State(state.clone())),
+                    },
+                ),
+                (
+                    Label::State(state),
+                    Block {
+                        instructions: Vec::new(),
+                        exit: Exit::End,
+                    },
+                ),
+            ]),
+        },
+    ];
+
+    for program in programs {
+        cache
+            .store(&key, &program, &Stats::default())
+            .expect("program should be stored");
+        assert!(matches!(cache.load(&key), Err(Error::Corrupt)), "{program}");
+    }
+
+    assert_eq!(run("12+.@", &dir), "3 ");
+}
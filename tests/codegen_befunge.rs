@@ -0,0 +1,98 @@
+//! Tests for generating Befunge playfields from programs.
+
+use std::process::Command;
+
+use fungus::{
+    Builder,
+    codegen::{self, Error, Result, Target},
+    interpret::{BufferIo, ScriptedRandom},
+    ir::state::Direction,
+};
+
+/// Programs with deterministic output, and their input.
+const PROGRAMS: [(&str, &str); 8] = [
+    ("\"!dlroW ,olleH\">:#,_@", ""),
+    ("9>:.:#v_@\n ^  -1<", ""),
+    ("&&+.~,~,@", "3\n4\nab"),
+    ("10/.93%.05-2/.05-2%.@", "5\n"),
+    ("\"~~\"*:*:*.25*,\"é\",@", ""),
+    ("\"a\"50p50g,@", ""),
+    ("\"é\"96p96g,@\n\n\n\n\n\n         x", ""),
+    ("v>1.@\n>?2.@\n >3.@\n ^4.@", ""),
+];
+
+/// Generates a Befunge playfield from source code.
+fn generate(source: &str) -> Result<String> {
+    let compiled = Builder::new(source).compile();
+    codegen::generate_program(compiled.program(), compiled.playfield(), Target::Befunge)
+}
+
+/// Interprets source code with input and returns its output.
+fn interpret(source: &str, input: &str) -> String {
+    let mut random = ScriptedRandom::new([Direction::Up, Direction::Left, Direction::Down])
+        .expect("directions should not be empty");
+
+    let mut io = BufferIo::new(input);
+    Builder::new(source)
+        .compile()
+        .run_with(&mut io, &mut random)
+        .expect("program should not exceed any limits");
+    io.into_output()
+}
+
+/// Generated playfields print the same output as their original playfields.
+#[test]
+fn matches_interpreter() {
+    for (source, input) in PROGRAMS {
+        let generated = generate(source).expect("playfield should be generated");
+        assert_eq!(
+            interpret(&generated, input),
+            interpret(source, input),
+            "{source}\n\n{generated}"
+        );
+    }
+}
+
+/// Cells with values that can't be stored as characters are put in the data
+/// row when the program starts.
+#[test]
+fn puts_control_characters() {
+    let generated = generate("50g.@\t").expect("playfield should be generated");
+    assert!(!generated.contains('\t'), "{generated}");
+    assert_eq!(interpret(&generated, ""), "9 ");
+}
+
+/// Programs with get or put commands whose positions are not known are
+/// rejected.
+#[test]
+fn rejects_dynamic_playfields() {
+    for source in ["&&g.@", "&&&p@"] {
+        assert!(matches!(generate(source), Err(Error::DynamicPlayfield)));
+    }
+}
+
+/// Generated playfields that are larger than 80x25 cells are rejected.
+#[test]
+fn rejects_large_playfields() {
+    let source = "9.".repeat(39) + "@";
+    assert!(matches!(
+        generate(&source),
+        Err(Error::PlayfieldTooLarge(w, 1)) if w > 80
+    ));
+}
+
+/// The command line exits with an error if a playfield can't be generated.
+#[test]
+fn reports_errors_from_command_line() {
+    let output = Command::new(env!("CARGO_BIN_EXE_fungus"))
+        .args(["--emit", "befunge", "-e", "&&g.@"])
+        .output()
+        .expect("fungus should run");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .starts_with("error: could not generate source code: ")
+    );
+}
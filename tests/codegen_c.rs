@@ -0,0 +1,145 @@
+//! Tests for running programs generated by the C backend.
+
+use std::{
+    env,
+    fs::{self, File},
+    io::{Read, Write},
+    path::PathBuf,
+    process::{self, Command, Stdio},
+};
+
+use fungus::{
+    Builder,
+    codegen::{self, Target},
+    interpret::{BufferIo, OsRandom},
+};
+
+/// Programs with deterministic output, and their input.
+const PROGRAMS: [(&str, &str); 9] = [
+    ("\"!dlroW ,olleH\">:#,_@", ""),
+    ("9>:.:#v_@\n ^  -1<", ""),
+    ("&&+.~,~,@", "3\n4\nab"),
+    ("10/.93%.05-2/.05-2%.@", "5\n"),
+    ("10%.@", "x\n"),
+    ("\"~~\"*:*:*.@", ""),
+    ("\"a\"50p50g,@", ""),
+    ("0>:2%\"1\"+92p v\n\n |!`9:+1.    <\n @", ""),
+    (
+        "\"a\"&2p0|\n       >\"7\"83p\"v\"&&pv\n       ^            <\n       >1.@",
+        "0\n7\n2\n",
+    ),
+];
+
+/// A temporary directory for compiling a generated program.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates a new temporary directory with a name.
+    fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("fungus-c-{name}-{}", process::id()));
+        fs::create_dir_all(&path).expect("temporary directory should be created");
+        Self(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Interprets source code with input and returns its output.
+fn interpret(source: &str, input: &str) -> String {
+    let mut io = BufferIo::new(input);
+    Builder::new(source)
+        .compile()
+        .run_with(&mut io, &mut OsRandom)
+        .expect("program should not exceed any limits");
+    io.into_output()
+}
+
+/// Generates C source code from source code, compiles it in a temporary
+/// directory, and returns a command to run it.
+fn compile(source: &str, dir: &TempDir) -> Command {
+    let compiled = Builder::new(source).compile();
+    let code = codegen::generate_program(compiled.program(), compiled.playfield(), Target::C)
+        .expect("source code should be generated");
+    let (c_path, exe_path) = (dir.0.join("program.c"), dir.0.join("program"));
+    fs::write(&c_path, code).expect("C source code should be written");
+
+    let status = Command::new("cc")
+        .arg("-o")
+        .arg(&exe_path)
+        .arg(&c_path)
+        .status()
+        .expect("a C compiler should run");
+    assert!(status.success(), "generated C should compile");
+    Command::new(exe_path)
+}
+
+/// Generated programs print the same output as interpreted programs.
+#[test]
+fn matches_interpreter() {
+    for (index, (source, input)) in PROGRAMS.into_iter().enumerate() {
+        let dir = TempDir::new(&format!("program-{index}"));
+        let mut child = compile(source, &dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("generated program should run");
+
+        child
+            .stdin
+            .take()
+            .expect("standard input should be piped")
+            .write_all(input.as_bytes())
+            .expect("standard input should be written");
+
+        let output = child.wait_with_output().expect("program should finish");
+        assert!(output.status.success(), "{source}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            interpret(source, input),
+            "{source}"
+        );
+    }
+}
+
+/// Generated programs stop quietly when their output is closed.
+#[test]
+fn stops_when_output_is_closed() {
+    let dir = TempDir::new("closed");
+    let mut child = compile(">\"a\",v\n^    <", &dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("generated program should run");
+
+    let mut stdout = child
+        .stdout
+        .take()
+        .expect("standard output should be piped");
+    let mut buffer = [0; 16];
+    stdout
+        .read_exact(&mut buffer)
+        .expect("output should be read");
+    drop(stdout);
+
+    let output = child.wait_with_output().expect("program should finish");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty());
+}
+
+/// Generated programs exit with code 4 if their output can't be written.
+#[cfg(target_os = "linux")]
+#[test]
+fn exits_with_code_4_for_output() {
+    let dir = TempDir::new("full");
+    let full = File::create("/dev/full").expect("/dev/full should open");
+    let status = compile("\"iH\",,@", &dir)
+        .stdout(full)
+        .status()
+        .expect("generated program should run");
+
+    assert_eq!(status.code(), Some(4));
+}
@@ -0,0 +1,176 @@
+//! Tests for running programs generated by the WebAssembly text backend.
+
+use std::collections::VecDeque;
+
+use fungus::{
+    Builder,
+    codegen::{self, Target},
+    interpret::{BufferIo, RandomSource, ScriptedRandom},
+    ir::state::Direction,
+};
+use wasmi::{Caller, Engine, Extern, Linker, Module, Store};
+
+/// Programs with deterministic output, and their input.
+const PROGRAMS: [(&str, &str); 8] = [
+    ("\"!dlroW ,olleH\">:#,_@", ""),
+    ("9>:.:#v_@\n ^  -1<", ""),
+    ("&&+.~,~,@", "3\n4\nab"),
+    ("10/.93%.05-2/.05-2%.@", "5\n"),
+    ("10%.&.@", "x\n12\n"),
+    ("\"~~\"*:*:*.25*,\"é\",@", ""),
+    ("\"a\"50p50g,@", ""),
+    ("v>1.@\n>?2.@\n >3.@\n ^4.@", ""),
+];
+
+/// The state of a host running a generated module.
+struct Host {
+    /// The remaining input.
+    input: VecDeque<char>,
+
+    /// The output.
+    output: Vec<u8>,
+
+    /// The random source.
+    random: ScriptedRandom,
+
+    /// The arguments that `recompile` was called with.
+    recompile: Option<[i32; 4]>,
+}
+
+/// Returns a random source that repeats every direction.
+fn random() -> ScriptedRandom {
+    ScriptedRandom::new([
+        Direction::Up,
+        Direction::Right,
+        Direction::Left,
+        Direction::Down,
+    ])
+    .expect("directions should not be empty")
+}
+
+/// Interprets source code with input and returns its output.
+fn interpret(source: &str, input: &str) -> String {
+    let mut io = BufferIo::new(input);
+    Builder::new(source)
+        .compile()
+        .run_with(&mut io, &mut random())
+        .expect("program should not exceed any limits");
+    io.into_output()
+}
+
+/// Generates a WebAssembly text module from source code and runs it with
+/// input. Returns the value returned by `run` and the host.
+fn run_module(source: &str, input: &str) -> (i32, Host) {
+    let compiled = Builder::new(source).compile();
+    let code = codegen::generate_program(compiled.program(), compiled.playfield(), Target::Wat)
+        .expect("source code should be generated");
+    let wasm = wat::parse_str(code).expect("generated module should assemble");
+
+    let engine = Engine::default();
+    let module = Module::new(&engine, &wasm[..]).expect("generated module should be valid");
+    let host = Host {
+        input: input.chars().collect(),
+        output: Vec::new(),
+        random: random(),
+        recompile: None,
+    };
+
+    let mut store = Store::new(&engine, host);
+    let mut linker = Linker::new(&engine);
+    linker
+        .func_wrap("fungus", "output", output)
+        .expect("output should be defined");
+    linker
+        .func_wrap("fungus", "input_char", |mut caller: Caller<'_, Host>| {
+            caller
+                .data_mut()
+                .input
+                .pop_front()
+                .map_or(-1, |c| i32::try_from(u32::from(c)).unwrap_or(-1))
+        })
+        .expect("input_char should be defined");
+    linker
+        .func_wrap("fungus", "input_int", input_int)
+        .expect("input_int should be defined");
+    linker
+        .func_wrap(
+            "fungus",
+            "random",
+            |mut caller: Caller<'_, Host>| match caller.data_mut().random.next_direction() {
+                Direction::Right => 0,
+                Direction::Down => 1,
+                Direction::Left => 2,
+                Direction::Up => 3,
+            },
+        )
+        .expect("random should be defined");
+    linker
+        .func_wrap(
+            "fungus",
+            "recompile",
+            |mut caller: Caller<'_, Host>, x: i32, y: i32, mode: i32, direction: i32| {
+                caller.data_mut().recompile = Some([x, y, mode, direction]);
+            },
+        )
+        .expect("recompile should be defined");
+
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .and_then(|i| i.start(&mut store))
+        .expect("generated module should be instantiated");
+    let status = instance
+        .get_typed_func::<(), i32>(&store, "run")
+        .expect("generated module should export run")
+        .call(&mut store, ())
+        .expect("generated module should not trap");
+
+    (status, store.into_data())
+}
+
+/// Writes bytes from the module's memory to the output.
+fn output(mut caller: Caller<'_, Host>, ptr: i32, len: i32) {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .expect("generated module should export memory");
+    let (ptr, len) = (ptr as usize, len as usize);
+    let bytes = memory.data(&caller)[ptr..ptr + len].to_vec();
+    caller.data_mut().output.extend(bytes);
+}
+
+/// Reads a line of input and parses it as an integer.
+fn input_int(mut caller: Caller<'_, Host>) -> i32 {
+    let input = &mut caller.data_mut().input;
+    let length = input
+        .iter()
+        .position(|c| *c == '\n')
+        .map_or(input.len(), |i| i + 1);
+
+    let line: String = input.drain(..length).collect();
+    line.trim().parse().unwrap_or(-1)
+}
+
+/// Generated modules print the same output as interpreted programs.
+#[test]
+fn matches_interpreter() {
+    for (source, input) in PROGRAMS {
+        let (status, host) = run_module(source, input);
+        assert_eq!(status, 0, "{source}");
+        assert_eq!(
+            String::from_utf8(host.output).expect("output should be UTF-8"),
+            interpret(source, input),
+            "{source}"
+        );
+        assert!(host.recompile.is_none(), "{source}");
+    }
+}
+
+/// Generated modules ask the host to recompile them from the position after a
+/// put command that changes the program.
+#[test]
+fn requests_recompilation() {
+    let (status, host) = run_module("0>:2%\"1\"+92p v\n\n |!`9:+1.    <\n @", "");
+    assert_eq!(status, 1);
+    assert!(host.output.is_empty());
+    assert_eq!(host.recompile, Some([12, 0, 0, 0]));
+}
@@ -0,0 +1,108 @@
+//! Tests for dumping compiled programs in machine-readable formats.
+
+use std::process::Command;
+
+use fungus::{
+    Builder,
+    dump::{self, Format},
+};
+
+/// Compiles source code and dumps it in a format.
+fn dump(source: &str, format: Format) -> String {
+    dump::dump_program(Builder::new(source).compile().program(), format)
+}
+
+/// Programs are dumped as JSON objects with a `blocks` array.
+#[test]
+fn dumps_json() {
+    assert_eq!(
+        dump("12+.@", Format::Json),
+        r#"{
+  "blocks": [
+    {
+      "label": {
+        "kind": "main"
+      },
+      "instructions": [
+        {
+          "op": "print",
+          "string": "3 "
+        }
+      ],
+      "exit": {
+        "kind": "end"
+      }
+    }
+  ]
+}"#
+    );
+}
+
+/// States are dumped as objects with their coordinates, mode, and direction.
+#[test]
+fn dumps_json_states() {
+    let json = dump("&1p", Format::Json);
+    assert!(json.contains(
+        r#""target": {
+          "kind": "state",
+          "x": 0,
+          "y": 0,
+          "mode": "command",
+          "direction": "right"
+        }"#
+    ));
+    assert!(json.contains(r#""op": "input_int""#));
+}
+
+/// Quotes, backslashes, and control characters are escaped in JSON strings.
+#[test]
+fn escapes_json_strings() {
+    let json = dump("25*,\"\\\",9,1,\"!\"1+,\"é\",@", Format::Json);
+    assert!(json.contains(r#""string": "\n\\\t\u0001\"é""#), "{json}");
+}
+
+/// The command line option prints the program as JSON instead of running it.
+#[test]
+fn prints_json_from_command_line() {
+    let output = Command::new(env!("CARGO_BIN_EXE_fungus"))
+        .args(["--dump-format", "json", "-e", "12+.@"])
+        .output()
+        .expect("fungus should run");
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        dump("12+.@", Format::Json) + "\n"
+    );
+}
+
+/// Programs are dumped as DOT digraphs with a node for each block and labeled
+/// edges for each branch of an exit.
+#[test]
+fn dumps_dot() {
+    let dot = dump("#@?1.@", Format::Dot);
+    assert!(dot.starts_with("digraph program {\n"));
+    assert!(dot.ends_with("\n}"));
+    assert!(dot.contains(
+        "    \"x3_y0_command_right\" [label=\"x3_y0_command_right:\\lprint   \\\"1 \\\"\\lend\\l\", \
+         peripheries=2];\n"
+    ));
+
+    for direction in ["right", "down", "left", "up"] {
+        assert_eq!(
+            dot.matches(&format!(" [label=\"{direction}\"];\n")).count(),
+            3
+        );
+    }
+}
+
+/// Quotes and backslashes are escaped in DOT strings, and lines are
+/// left-justified.
+#[test]
+fn escapes_dot_strings() {
+    let dot = dump("25*,\"\\\",9,1,\"!\"1+,\"é\",@", Format::Dot);
+    assert!(
+        dot.contains(r#"[label="main:\lprint   \"\\n\\\\\\t\\u{1}\\\"\\u{e9}\"\lend\l""#),
+        "{dot}"
+    );
+}
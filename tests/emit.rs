@@ -0,0 +1,103 @@
+//! Tests for printing programs after compilation stages.
+
+use std::process::Command;
+
+use fungus::{Builder, Stage, optimize::Step};
+
+/// Runs Fungus on source code with an `--emit` option and returns the printed
+/// stage headers and the remaining output after the last printed program.
+fn emit(stages: &str, source: &str) -> (Vec<String>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_fungus"))
+        .args(["--emit", stages, "-e", source])
+        .output()
+        .expect("fungus should run");
+
+    assert!(output.status.success());
+    let output = String::from_utf8(output.stdout).expect("output should be UTF-8");
+    let headers = output
+        .lines()
+        .filter(|l| l.starts_with("== "))
+        .map(Into::into)
+        .collect();
+
+    let rest = output.rsplit("\n\n").next().unwrap_or_default().into();
+    (headers, rest)
+}
+
+/// The observer sees the parsed program and then the program after every step
+/// of every pass, ending with the compiled program.
+#[test]
+fn observes_stages() {
+    let mut stages = Vec::new();
+    let mut last_program = String::new();
+    let compiled = Builder::new("12+.@").compile_observed(|stage, program| {
+        stages.push(stage);
+        last_program = program.to_string();
+    });
+
+    assert!(matches!(stages[0], Stage::Parse));
+    assert_eq!(last_program, compiled.program().to_string());
+
+    let steps: Vec<_> = stages[1..]
+        .iter()
+        .map(|s| match s {
+            Stage::Step(r) => *r,
+            Stage::Parse => panic!("the program should only be parsed once"),
+        })
+        .collect();
+
+    assert_eq!(steps.len() % Step::ALL.len(), 0);
+    for (index, run) in steps.iter().enumerate() {
+        assert_eq!(run.pass, index / Step::ALL.len() + 1);
+        assert!(run.step == Step::ALL[index % Step::ALL.len()]);
+    }
+
+    assert!(steps.iter().any(|r| r.changed));
+    assert!(
+        !steps[steps.len() - Step::ALL.len()..]
+            .iter()
+            .any(|r| r.changed)
+    );
+}
+
+/// Every step is labeled with its pass number and name, and marked if it did
+/// not change the program. The program is still run after being printed.
+#[test]
+fn emits_steps() {
+    let (headers, rest) = emit("parse,steps", "12+.@");
+    assert_eq!(
+        headers,
+        [
+            "== parse ==",
+            "== pass 1: merge-blocks ==",
+            "== pass 1: thread-jumps (unchanged) ==",
+            "== pass 1: remove-unreachable-blocks (unchanged) ==",
+            "== pass 1: replace-instructions ==",
+            "== pass 1: replace-jumps-to-exits (unchanged) ==",
+            "== pass 1: optimize-branches (unchanged) ==",
+            "== pass 1: analyze-ranges (unchanged) ==",
+            "== pass 2: merge-blocks (unchanged) ==",
+            "== pass 2: thread-jumps (unchanged) ==",
+            "== pass 2: remove-unreachable-blocks (unchanged) ==",
+            "== pass 2: replace-instructions (unchanged) ==",
+            "== pass 2: replace-jumps-to-exits (unchanged) ==",
+            "== pass 2: optimize-branches (unchanged) ==",
+            "== pass 2: analyze-ranges (unchanged) ==",
+        ]
+    );
+    assert_eq!(rest, "3 ");
+}
+
+/// Only steps that changed the program are printed for `changed-steps`.
+#[test]
+fn emits_changed_steps() {
+    let (headers, rest) = emit("changed-steps", "12+.@");
+    assert_eq!(
+        headers,
+        [
+            "== pass 1: merge-blocks ==",
+            "== pass 1: replace-instructions ==",
+        ]
+    );
+    assert_eq!(rest, "3 ");
+}
@@ -0,0 +1,48 @@
+//! Tests for interpreting programs with an in-memory I/O backend.
+
+use fungus::{
+    Builder,
+    interpret::{BufferIo, OsRandom},
+};
+
+/// Runs source code with input and returns its output.
+fn run(source: &str, input: &str) -> String {
+    let mut io = BufferIo::new(input);
+    Builder::new(source)
+        .compile()
+        .run_with(&mut io, &mut OsRandom)
+        .expect("program should not exceed any limits");
+    io.into_output()
+}
+
+/// Characters and integers are written to the output buffer.
+#[test]
+fn captures_output() {
+    assert_eq!(run("\"!olleH\",,,,,,@", ""), "Hello!");
+    assert_eq!(run("12+.55*.@", ""), "3 25 ");
+}
+
+/// Characters are read one at a time and integers are read a line at a time.
+#[test]
+fn reads_input() {
+    assert_eq!(run("~,~,&.&.@", "ab12\n-3\n"), "ab12 -3 ");
+}
+
+/// Reading past the end of the input pushes -1.
+#[test]
+fn reads_past_end_of_input() {
+    assert_eq!(run("~.&.@", ""), "-1 -1 ");
+}
+
+/// A line that is not an integer is consumed and reads as -1.
+#[test]
+fn reads_invalid_integers() {
+    assert_eq!(run("&.&.@", "abc\n7\n"), "-1 7 ");
+}
+
+/// The prompt for dividing by zero is written to the output buffer and the
+/// result is read from the input buffer.
+#[test]
+fn prompts_for_division_by_zero() {
+    assert_eq!(run("10/.@", "5\n"), "What do you want 1/0 to be? 5 ");
+}
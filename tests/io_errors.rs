@@ -0,0 +1,119 @@
+//! Tests for reporting I/O errors while interpreting programs.
+
+use std::{
+    io::{self, Read, Write},
+    process::{Command, Stdio},
+};
+
+use fungus::{
+    Builder,
+    interpret::{Error, Io, OsRandom},
+};
+
+/// A program that prints `a` forever.
+const SOURCE: &str = ">\"a\",v\n^    <";
+
+/// An I/O backend with no input that fails after some output.
+struct FailingIo {
+    /// The number of strings that can be output before failing.
+    remaining: usize,
+}
+
+impl Io for FailingIo {
+    fn input_char(&mut self) -> io::Result<Option<char>> {
+        Ok(None)
+    }
+
+    fn input_int(&mut self) -> io::Result<Option<i32>> {
+        Ok(None)
+    }
+
+    fn output_str(&mut self, _: &str) -> io::Result<()> {
+        if self.remaining == 0 {
+            return Err(io::ErrorKind::StorageFull.into());
+        }
+
+        self.remaining -= 1;
+        Ok(())
+    }
+}
+
+/// Returns a command to run Fungus with command line arguments.
+fn fungus(args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_fungus"));
+    command.args(args);
+    command
+}
+
+/// An error from an I/O backend stops the program and is returned.
+#[test]
+fn returns_io_errors() {
+    let result = Builder::new(SOURCE)
+        .compile()
+        .run_with(&mut FailingIo { remaining: 10 }, &mut OsRandom);
+
+    assert!(matches!(
+        result,
+        Err(Error::Io(e)) if e.kind() == io::ErrorKind::StorageFull
+    ));
+}
+
+/// Closing the output stops the program quietly.
+#[test]
+fn stops_when_output_is_closed() {
+    let mut child = fungus(&["-e", ">\"a\",v", "-e", "^    <"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("fungus should run");
+
+    let mut stdout = child
+        .stdout
+        .take()
+        .expect("standard output should be piped");
+    let mut buffer = [0; 16];
+    stdout
+        .read_exact(&mut buffer)
+        .expect("output should be read");
+    assert_eq!(&buffer, b"aaaaaaaaaaaaaaaa");
+    drop(stdout);
+
+    let output = child.wait_with_output().expect("fungus should finish");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty());
+}
+
+/// Failing to write output exits with code 4.
+#[cfg(target_os = "linux")]
+#[test]
+fn exits_with_code_4_for_output() {
+    let full = std::fs::File::create("/dev/full").expect("/dev/full should open");
+    let output = fungus(&["-e", "\"iH\",,@"])
+        .stdout(full)
+        .output()
+        .expect("fungus should run");
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("could not perform program I/O"));
+}
+
+/// Reading input that is not UTF-8 exits with code 4.
+#[test]
+fn exits_with_code_4_for_input() {
+    let mut child = fungus(&["-e", "~.@"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("fungus should run");
+
+    child
+        .stdin
+        .take()
+        .expect("standard input should be piped")
+        .write_all(b"\xff\n")
+        .expect("standard input should be written");
+
+    let output = child.wait_with_output().expect("fungus should finish");
+    assert_eq!(output.status.code(), Some(4));
+}
@@ -0,0 +1,129 @@
+//! Tests for compiling programs to machine code before running them.
+
+#![cfg(feature = "jit")]
+
+use std::collections::BTreeMap;
+
+use fungus::{
+    Block, Builder, Exit, Instruction, Label, Playfield, Program, Standard, Value,
+    interpret::{self, BufferIo, Error, Limit, Limits, SeededRandom},
+    optimize::Options,
+};
+
+/// Programs and their input.
+const PROGRAMS: [(&str, &str); 9] = [
+    ("\"!dlroW ,olleH\">:#,_@", ""),
+    ("9>:.:#v_@\n ^  -1<", ""),
+    ("&&+.~,~,@", "3\n4\nab"),
+    ("10/.93%.05-2/.05-2%.@", "5\n"),
+    ("\"~~\"*:*:*.\\$$$:.@", ""),
+    ("\"a\"50p50g,@", ""),
+    ("0>:2%\"1\"+92p v\n\n |!`9:+1.    <\n @", ""),
+    (
+        "\"a\"&2p0|\n       >\"7\"83p\"v\"&&pv\n       ^            <\n       >1.@",
+        "0\n7\n2\n",
+    ),
+    ("v>1.@\n>?2.@\n >3.@\n ^4.@", ""),
+];
+
+/// Runs source code with input and returns its output and final playfield
+/// cells, either with the JIT or with the bytecode interpreter.
+fn run(source: &str, input: &str, jit: bool) -> (String, Vec<Option<i32>>) {
+    let mut io = BufferIo::new(input);
+    let mut random = SeededRandom::new(7);
+    let compiled = Builder::new(source).compile();
+    let playfield = if jit {
+        compiled.run_jit_with(&mut io, &mut random)
+    } else {
+        compiled.run_with(&mut io, &mut random)
+    }
+    .expect("program should not exceed any limits");
+
+    (io.into_output(), cells(&playfield))
+}
+
+/// Returns the cells of a playfield in row-major order.
+fn cells(playfield: &Playfield) -> Vec<Option<i32>> {
+    let (width, height) = playfield.bounds();
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| playfield.get(x, y).map(Value::into_i32)))
+        .collect()
+}
+
+/// Compiled programs produce the same output and final playfield as
+/// interpreted programs.
+#[test]
+fn matches_interpreter() {
+    for (source, input) in PROGRAMS {
+        assert_eq!(
+            run(source, input, true),
+            run(source, input, false),
+            "{source}"
+        );
+    }
+}
+
+/// Compiled programs stop when limits are exceeded.
+#[test]
+fn limits_compiled_programs() {
+    let limits = [
+        Limits {
+            max_steps: Some(100),
+            ..Limits::default()
+        },
+        Limits {
+            max_stack: Some(100),
+            ..Limits::default()
+        },
+    ];
+
+    for (limits, expected) in limits.into_iter().zip([Limit::Steps, Limit::Stack]) {
+        let result = Builder::new(">1<")
+            .limits(limits)
+            .compile()
+            .run_jit_with(&mut BufferIo::new(""), &mut SeededRandom::new(7));
+
+        assert!(matches!(result, Err(Error::LimitExceeded(l, _)) if l == expected));
+    }
+}
+
+/// Cells outside of the playfield are read as 0 and ignored when written
+/// instead of being accessed by machine code.
+#[test]
+fn ignores_cells_out_of_bounds() {
+    let instructions = vec![
+        Instruction::Push(5.into()),
+        Instruction::PutAt(0, 1),
+        Instruction::Push(6.into()),
+        Instruction::PutAt(1000, 0),
+        Instruction::GetAt(0, 1000),
+        Instruction::OutputInt,
+        Instruction::GetAt(0, 0),
+        Instruction::OutputInt,
+    ];
+
+    let program = Program {
+        blocks: BTreeMap::from([(
+            Label::Main,
+            Block {
+                instructions,
+                exit: Exit::End,
+            },
+        )]),
+    };
+
+    let mut playfield = Playfield::new("@", Standard::default());
+    let mut io = BufferIo::new("");
+    interpret::jit_program(
+        &program,
+        &mut playfield,
+        &mut io,
+        &mut SeededRandom::new(7),
+        Limits::default(),
+        &Options::default(),
+    )
+    .expect("program should not exceed any limits");
+
+    assert_eq!(io.output(), "0 64 ");
+    assert_eq!(cells(&playfield), [Some('@' as i32)]);
+}
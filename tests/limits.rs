@@ -0,0 +1,100 @@
+//! Tests for limiting the resources used by interpreted programs.
+
+use std::{
+    env, fs,
+    process::{self, Command},
+    time::Duration,
+};
+
+use fungus::{
+    Builder,
+    interpret::{BufferIo, Error, Limit, Limits, OsRandom, Result},
+};
+
+/// Runs source code with limits and returns its output.
+fn run(source: &str, limits: Limits) -> Result<String> {
+    let mut io = BufferIo::new("");
+    Builder::new(source)
+        .limits(limits)
+        .compile()
+        .run_with(&mut io, &mut OsRandom)?;
+    Ok(io.into_output())
+}
+
+/// Returns the limit that was exceeded by a result.
+fn exceeded_limit(result: Result<String>) -> Option<Limit> {
+    match result {
+        Err(Error::LimitExceeded(l, _)) => Some(l),
+        _ => None,
+    }
+}
+
+/// Programs that stay within their limits run to completion.
+#[test]
+fn runs_within_limits() {
+    let limits = Limits {
+        max_steps: Some(100),
+        max_stack: Some(2),
+        max_time: Some(Duration::from_secs(60)),
+    };
+
+    assert_eq!(run("12+.@", limits).ok().as_deref(), Some("3 "));
+}
+
+/// An infinite loop exceeds the step limit.
+#[test]
+fn limits_steps() {
+    let limits = Limits {
+        max_steps: Some(1000),
+        ..Limits::default()
+    };
+
+    assert_eq!(exceeded_limit(run("> <", limits)), Some(Limit::Steps));
+}
+
+/// A loop that keeps pushing values exceeds the stack limit.
+#[test]
+fn limits_stack() {
+    let limits = Limits {
+        max_stack: Some(1000),
+        ..Limits::default()
+    };
+
+    assert_eq!(exceeded_limit(run(">1<", limits)), Some(Limit::Stack));
+}
+
+/// An infinite loop exceeds the time limit.
+#[test]
+fn limits_time() {
+    let limits = Limits {
+        max_time: Some(Duration::from_millis(10)),
+        ..Limits::default()
+    };
+
+    assert_eq!(exceeded_limit(run("> <", limits)), Some(Limit::Time));
+}
+
+/// Exceeding a limit from the command line reports it and exits with code 3.
+#[test]
+fn exits_with_code_3() {
+    let path = env::temp_dir().join(format!("fungus-limits-{}.bf", process::id()));
+    fs::write(&path, ">1<").expect("source file should be written");
+
+    for (option, limit) in [
+        ("--max-steps", "step limit"),
+        ("--max-stack", "stack limit"),
+        ("--max-time", "time limit"),
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_fungus"))
+            .args([option, "10"])
+            .arg(&path)
+            .output()
+            .expect("fungus should run");
+
+        assert_eq!(output.status.code(), Some(3), "{option}");
+        let message = String::from_utf8_lossy(&output.stderr);
+        assert!(message.contains(&format!("{limit} exceeded")), "{message}");
+    }
+
+    let _ = fs::remove_file(&path);
+}
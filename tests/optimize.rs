@@ -0,0 +1,146 @@
+//! Tests for optimization levels and disabled optimization steps.
+
+use std::process::Command;
+
+use fungus::{
+    Builder, Playfield, Stage, Standard,
+    interpret::{BufferIo, OsRandom},
+    optimize::{Level, Options, Step},
+    parse,
+};
+
+/// A program that counts down from 9 to 0.
+const COUNTDOWN: [&str; 2] = ["9>:.:#v_@", " ^  -1<"];
+
+/// Compiles source code with options and returns the program's pseudo-assembly
+/// and the optimization steps that were run.
+fn compile(source: &str, options: Options) -> (String, Vec<(usize, Step)>) {
+    let mut runs = Vec::new();
+    let compiled = Builder::new(source)
+        .options(options)
+        .compile_observed(|stage, _| {
+            if let Stage::Step(r) = stage {
+                runs.push((r.pass, r.step));
+            }
+        });
+
+    (compiled.program().to_string(), runs)
+}
+
+/// Returns options with an optimization level.
+fn level(level: Level) -> Options {
+    Options {
+        level,
+        ..Options::default()
+    }
+}
+
+/// Runs Fungus with command line arguments and returns its standard output.
+fn run_cli(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_fungus"))
+        .args(args)
+        .output()
+        .expect("fungus should run");
+
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).expect("output should be UTF-8")
+}
+
+/// Optimization level 0 leaves the program as it was parsed.
+#[test]
+fn does_not_optimize_at_level_0() {
+    let source = COUNTDOWN.join("\n");
+    let (program, runs) = compile(&source, level(Level::None));
+    let (parsed, _) = parse::parse_program(&Playfield::new(&source, Standard::default()));
+    assert_eq!(program, parsed.to_string());
+    assert!(runs.is_empty());
+}
+
+/// Optimization level 1 runs a single pass of every step, and level 2 runs
+/// passes until a pass makes no changes.
+#[test]
+fn runs_passes_for_levels() {
+    let source = COUNTDOWN.join("\n");
+    let (_, runs) = compile(&source, level(Level::Once));
+    assert!(runs == Step::ALL.map(|s| (1, s)));
+
+    let (_, runs) = compile(&source, level(Level::Full));
+    assert!(runs.len() > Step::ALL.len());
+    assert_eq!(runs.len() % Step::ALL.len(), 0);
+}
+
+/// Disabled steps are not run at any level.
+#[test]
+fn disables_steps() {
+    for level in [Level::Once, Level::Full] {
+        let options = Options {
+            level,
+            disabled_steps: vec![Step::ReplaceInstructions],
+        };
+
+        let (program, runs) = compile("12+.@", options);
+        assert!(program.contains("binary  +"), "{program}");
+        assert!(!runs.iter().any(|(_, s)| *s == Step::ReplaceInstructions));
+    }
+}
+
+/// Loads of string mode cells are replaced with constants if the program never
+/// writes to them, and kept if a put with a dynamic position may write to them.
+#[test]
+fn folds_cells_that_are_never_written() {
+    let (program, _) = compile("\"ab\",,@", level(Level::Full));
+    assert!(!program.contains("getat"), "{program}");
+    assert!(program.contains("print   \"ba\""), "{program}");
+
+    let (program, _) = compile("\"ab\",,&&&p@", level(Level::Full));
+    assert!(program.contains("getat   1, 0"), "{program}");
+    assert!(program.contains("getat   2, 0"), "{program}");
+}
+
+/// Programs behave the same at every optimization level.
+#[test]
+fn preserves_behavior_at_every_level() {
+    for level in [Level::None, Level::Once, Level::Full] {
+        let mut io = BufferIo::new("");
+        Builder::new(&COUNTDOWN.join("\n"))
+            .options(Options {
+                level,
+                ..Options::default()
+            })
+            .compile()
+            .run_with(&mut io, &mut OsRandom)
+            .expect("program should not exceed any limits");
+
+        assert_eq!(io.output(), "9 8 7 6 5 4 3 2 1 0 ");
+    }
+}
+
+/// The command line options set the optimization level and disabled steps.
+#[test]
+fn sets_options_from_command_line() {
+    assert!(run_cli(&["-O0", "--dump", "-e", "12+.@"]).contains("push    1"));
+    assert!(run_cli(&["-O1", "--dump", "-e", "12+.@"]).contains("print   \"3 \""));
+
+    let program = run_cli(&[
+        "--no-opt",
+        "replace-instructions,merge-blocks",
+        "--dump",
+        "-e",
+        "12+.@",
+    ]);
+    assert!(program.contains("binary  +"));
+    assert!(program.contains("jump"));
+
+    for args in [
+        ["-O", "0", "-e", COUNTDOWN[0], "-e", COUNTDOWN[1]],
+        ["-O", "2", "-e", COUNTDOWN[0], "-e", COUNTDOWN[1]],
+    ] {
+        assert_eq!(run_cli(&args), "9 8 7 6 5 4 3 2 1 0 ");
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_fungus"))
+        .args(["-O3", "-e", "@"])
+        .output()
+        .expect("fungus should run");
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,100 @@
+//! Tests for choosing random directions with the `?` command.
+
+use std::{
+    env, fs,
+    process::{self, Command},
+};
+
+use fungus::{
+    Builder,
+    interpret::{BufferIo, RandomSource, ScriptedRandom, SeededRandom},
+    ir::state::Direction,
+};
+
+/// A program that prints `1`, `2`, or `3` for nine random directions to the
+/// left, right, or down. Choosing up chooses again.
+const SOURCE: &str = "\
+9>:#v_@
+    1
+    -
+    v
+ ^.1?2.v
+    3
+    .
+ ^  <  <";
+
+/// Runs the program with a random source and returns its output.
+fn run(random: &mut impl RandomSource) -> String {
+    let mut io = BufferIo::new("");
+    Builder::new(SOURCE)
+        .compile()
+        .run_with(&mut io, random)
+        .expect("program should not exceed any limits");
+    io.into_output()
+}
+
+/// Runs Fungus on the program with command line arguments and returns its
+/// standard output, or its standard error if it failed.
+fn run_cli(name: &str, args: &[&str]) -> Result<String, String> {
+    let path = env::temp_dir().join(format!("fungus-random-{name}-{}.bf", process::id()));
+    fs::write(&path, SOURCE).expect("source file should be written");
+    let output = Command::new(env!("CARGO_BIN_EXE_fungus"))
+        .args(args)
+        .arg(&path)
+        .output()
+        .expect("fungus should run");
+
+    let _ = fs::remove_file(&path);
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).into())
+    }
+}
+
+/// Runs with the same seed choose the same directions.
+#[test]
+fn reproduces_seeded_runs() {
+    let output = run(&mut SeededRandom::new(5));
+    assert_eq!(output.len(), 18);
+    assert_eq!(run(&mut SeededRandom::new(5)), output);
+}
+
+/// A scripted random source repeats its directions.
+#[test]
+fn repeats_scripted_directions() {
+    use Direction::{Down, Left, Right, Up};
+
+    let mut random = ScriptedRandom::new([Right, Down, Left]).expect("script should not be empty");
+    assert_eq!(run(&mut random), "2 3 1 2 3 1 2 3 1 ");
+
+    let mut random = ScriptedRandom::new([Up, Right]).expect("script should not be empty");
+    assert_eq!(run(&mut random), "2 2 2 2 2 2 2 2 2 ");
+}
+
+/// A scripted random source can't be created without any directions.
+#[test]
+fn rejects_empty_scripts() {
+    assert!(ScriptedRandom::new([]).is_none());
+}
+
+/// The command line options choose seeded or scripted directions.
+#[test]
+fn runs_random_options() {
+    let seeded = run_cli("seed", &["--seed", "5"]);
+    assert_eq!(seeded, run_cli("seed-again", &["--seed", "5"]));
+    assert_eq!(seeded.map(|o| o.len()), Ok(18));
+
+    assert_eq!(
+        run_cli("script", &["--random-script", "rdl"]).as_deref(),
+        Ok("2 3 1 2 3 1 2 3 1 ")
+    );
+
+    for args in [
+        ["--random-script", "rdx"].as_slice(),
+        &["--random-script", ""],
+        &["--random-script", "r", "--seed", "5"],
+    ] {
+        assert!(run_cli("invalid", args).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+//! Tests for lowering gets and puts with the ranges of their coordinates.
+
+use fungus::{
+    Builder,
+    interpret::{BufferIo, OsRandom},
+};
+
+/// Compiles source code and returns its pseudo-assembly.
+fn compile(source: &str) -> String {
+    Builder::new(source).compile().program().to_string()
+}
+
+/// Runs source code with input and returns its output.
+fn run(source: &str, input: &str) -> String {
+    let mut io = BufferIo::new(input);
+    Builder::new(source)
+        .compile()
+        .run_with(&mut io, &mut OsRandom)
+        .expect("program should not exceed any limits");
+    io.into_output()
+}
+
+/// Puts whose in-bounds coordinates can't be reached by the program counter
+/// are lowered to data puts, which the program can read back.
+#[test]
+fn lowers_puts_outside_of_code() {
+    let source = "\"a\"~3%5+2p~3%5+2g,@\n\n         ";
+    let program = compile(source);
+    assert!(program.contains("putdata"), "{program}");
+    assert!(!program.contains("put     "), "{program}");
+    assert_eq!(run(source, "\u{1}\u{1}"), "a");
+}
+
+/// Gets and puts whose coordinates are always out of bounds are removed.
+#[test]
+fn removes_accesses_out_of_bounds() {
+    let source = "\"v\"~3%3-0p~3%3-0g.@";
+    let program = compile(source);
+    assert!(!program.contains("put"), "{program}");
+    assert!(!program.contains("get"), "{program}");
+    assert_eq!(run(source, "\u{2}\u{2}"), "0 ");
+}
+
+/// Coordinates that may overflow wrap around, so a put whose coordinates are
+/// computed from values that are only out of bounds before wrapping may still
+/// modify the program.
+#[test]
+fn keeps_puts_with_overflowing_coordinates() {
+    let source = [
+        "\"@\"44*:*:*~+44*:*:*~+*47*+1pv",
+        "                            1",
+        "                            .",
+        "                            @",
+    ]
+    .join("\n");
+
+    let program = compile(&source);
+    assert!(program.contains("put     "), "{program}");
+    assert_eq!(run(&source, "\0\0"), "");
+}
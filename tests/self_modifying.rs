@@ -0,0 +1,205 @@
+//! End-to-end tests for programs that modify themselves while running.
+
+use fungus::{
+    Builder, Playfield, Program, Standard, State, Value,
+    interpret::{BufferIo, Error, Limit, Limits, SeededRandom},
+    optimize::{Level, Options},
+    parse,
+};
+
+/// The maximum number of steps a test program may run for.
+const MAX_STEPS: u64 = 1_000_000;
+
+/// Asserts that a program prints the expected output for an input at every
+/// optimization level, both when interpreted and when compiled to machine code.
+fn assert_output(rows: &[&str], input: &str, expected: &str) {
+    let source = rows.join("\n");
+    for (name, level) in [
+        ("-O0", Level::None),
+        ("-O1", Level::Once),
+        ("-O2", Level::Full),
+    ] {
+        let builder = || {
+            Builder::new(&source)
+                .options(Options {
+                    level,
+                    ..Options::default()
+                })
+                .limits(Limits {
+                    max_steps: Some(MAX_STEPS),
+                    ..Limits::default()
+                })
+        };
+
+        let mut io = BufferIo::new(input);
+        builder()
+            .compile()
+            .run_with(&mut io, &mut SeededRandom::new(0))
+            .expect("program should not exceed any limits");
+        assert_eq!(io.output(), expected, "interpreted at {name}");
+
+        #[cfg(feature = "jit")]
+        {
+            let mut io = BufferIo::new(input);
+            builder()
+                .compile()
+                .run_jit_with(&mut io, &mut SeededRandom::new(0))
+                .expect("program should not exceed any limits");
+            assert_eq!(io.output(), expected, "compiled at {name}");
+        }
+    }
+}
+
+/// Asserts that a reparsed program contains every block of a program parsed
+/// from scratch.
+fn assert_reparsed(reparsed: &Program, playfield: &Playfield) {
+    let (parsed, _) = parse::parse_program(playfield);
+    for (label, block) in &parsed.blocks {
+        let reparsed_block = reparsed
+            .blocks
+            .get(label)
+            .unwrap_or_else(|| panic!("reparsed program should have a block for {label}"));
+        assert_eq!(reparsed_block.to_string(), block.to_string(), "{label}");
+    }
+}
+
+/// Reparsing a changed cell gives the same blocks as parsing the playfield
+/// from scratch, and blocks that become unreachable are kept for when they are
+/// reachable again.
+#[test]
+fn reparses_changed_cells() {
+    let mut playfield = Playfield::new(">   v\n    1\n    .\n    @", Standard::default());
+    let (mut program, _) = parse::parse_program(&playfield);
+    let block_count = program.blocks.len();
+
+    for value in [' ', 'v'] {
+        playfield.put(4, 0, value.into());
+        parse::reparse_program(&mut program, &playfield, [(4, 0)], State::default());
+        assert_reparsed(&program, &playfield);
+    }
+
+    assert_eq!(program.blocks.len(), block_count);
+}
+
+/// A program that changes the same cell on every iteration prints the same
+/// output after each recompilation.
+#[test]
+fn recompiles_changed_cells() {
+    assert_output(
+        &["0>:2%\"1\"+92p v", "", " |!`9:+1.    <", " @"],
+        "",
+        "1 2 1 2 1 2 1 2 1 2 ",
+    );
+}
+
+/// Cells written by puts that do not recompile the program must be reparsed
+/// when a later put recompiles it.
+#[test]
+fn reparses_cells_written_without_recompiling() {
+    assert_output(
+        &[
+            "\"a\"&2p0|",
+            "       >\"7\"83p\"v\"&&pv",
+            "       ^            <",
+            "       >1.@",
+        ],
+        "0\n7\n2\n",
+        "7 ",
+    );
+}
+
+/// Recompiled programs are optimized unless optimization is disabled, so they
+/// run in fewer steps.
+#[test]
+fn optimizes_recompiled_programs() {
+    let source = "0>:2%\"1\"+92p v\n\n |!`9:+1.    <\n @";
+    let run = |level| {
+        Builder::new(source)
+            .options(Options {
+                level,
+                ..Options::default()
+            })
+            .limits(Limits {
+                max_steps: Some(300),
+                ..Limits::default()
+            })
+            .compile()
+            .run_with(&mut BufferIo::new(""), &mut SeededRandom::new(0))
+    };
+
+    assert!(run(Level::Once).is_ok());
+    assert!(matches!(
+        run(Level::None),
+        Err(Error::LimitExceeded(Limit::Steps, _))
+    ));
+}
+
+/// A put with a dynamic position must compare its value with the value that
+/// the cell was parsed from, not a value written since without recompiling.
+#[test]
+fn compares_puts_with_parsed_cells() {
+    assert_output(
+        &[
+            "\"a\"&2p0|",
+            "       >\" \"83p\"x\"&&p\"v\"&&pv",
+            "       ^                  <",
+            "       >1.@",
+        ],
+        "0\n8\n3\n7\n2\n",
+        "0 ",
+    );
+}
+
+/// Changes only change a parsed program if they parse differently in a mode
+/// that the cell is reached in.
+#[test]
+fn detects_changes_to_parsed_cells() {
+    let (program, _) =
+        parse::parse_program(&Playfield::new("\"ab\" v@\n\n      .", Standard::default()));
+    let changes = |x, y, previous: char, value: char| {
+        parse::changes_program(&program, (x, y), Value::from(previous), Value::from(value))
+    };
+
+    assert!(!changes(4, 0, ' ', 'x'));
+    assert!(changes(4, 0, ' ', '>'));
+    assert!(!changes(1, 0, 'a', 'c'));
+    assert!(changes(1, 0, 'a', '"'));
+    assert!(!changes(6, 2, '.', ','));
+}
+
+/// Cells reached in string mode push their current values, and only recompile
+/// the program if a quote is added or removed.
+#[test]
+fn reads_changed_string_mode_cells() {
+    assert_output(&["&&&p\"xy\",,@"], "65\n5\n0\n", "yA");
+    assert_output(&["&&&p\"a\" .@"], "32\n6\n0\n", "112 ");
+}
+
+/// A guarded put that only checks the mode its program reaches a cell in must
+/// not leave blocks parsed for the cell in another mode stale.
+#[test]
+fn reparses_cells_written_by_guarded_puts() {
+    assert_output(
+        &[
+            ">\" \".&&&p    v",
+            "^            <",
+            "  ^          <",
+            "  >\"x\"20p&&&pv",
+        ],
+        "118\n13\n1\n60\n13\n1\n64\n13\n0\n",
+        "32 120 ",
+    );
+}
+
+/// Puts with constant positions that can be reached are lowered to guarded
+/// puts, which only recompile the program if the value changes how the cell
+/// is parsed in the modes that it is reached in.
+#[test]
+fn guards_reachable_puts() {
+    let program = Builder::new("\"a\"60p 1.@").compile().into_parts().0;
+    assert!(program.to_string().contains("putgrd"), "{program}");
+
+    assert_output(&["\"a\"60p 1.@"], "", "1 ");
+    assert_output(&["\"2\"60p 1+.@"], "", "3 ");
+    assert_output(&["\"c\"70p\"ab\",,@"], "", "bc");
+}
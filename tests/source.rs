@@ -0,0 +1,66 @@
+//! Tests for loading source code from the command line.
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+/// Runs Fungus with command line arguments and standard input and returns its
+/// output.
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_fungus"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("fungus should run");
+
+    child
+        .stdin
+        .take()
+        .expect("standard input should be piped")
+        .write_all(stdin.as_bytes())
+        .expect("standard input should be written");
+
+    child.wait_with_output().expect("fungus should finish")
+}
+
+/// Returns the standard output of a successful run.
+fn stdout(output: &Output) -> &str {
+    assert!(output.status.success(), "{output:?}");
+    str::from_utf8(&output.stdout).expect("output should be UTF-8")
+}
+
+/// Each `--eval` option is a row of the playfield.
+#[test]
+fn loads_eval_rows() {
+    assert_eq!(stdout(&run(&["-e", "\"iH\",,@"], "")), "Hi");
+    assert_eq!(stdout(&run(&["-e", "v", "--eval", ">\"iH\",,@"], "")), "Hi");
+}
+
+/// Inline source code still reads user input from the standard input stream.
+#[test]
+fn reads_input_with_eval_rows() {
+    assert_eq!(stdout(&run(&["-e", "&.@"], "42\n")), "42 ");
+}
+
+/// A path of `-` reads source code from the standard input stream, which then
+/// has no user input left.
+#[test]
+fn loads_stdin() {
+    assert_eq!(stdout(&run(&["-"], "v\n>\"iH\",,@")), "Hi");
+    assert_eq!(stdout(&run(&["-"], "&.~.@")), "-1 -1 ");
+}
+
+/// Source code can't be given both as a path and as inline rows, and must be
+/// given as one of them.
+#[test]
+fn rejects_invalid_sources() {
+    assert_eq!(run(&["-", "-e", "@"], "").status.code(), Some(2));
+    assert_eq!(run(&[], "").status.code(), Some(2));
+
+    let output = run(&["does-not-exist.bf"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("source file does not exist"));
+}
@@ -0,0 +1,127 @@
+//! Tests for following Befunge standards.
+
+use std::process::Command;
+
+use fungus::{
+    Builder, Playfield, Standard,
+    cache::Key,
+    interpret::{BufferIo, Limits, SeededRandom},
+    optimize::{Level, Options},
+};
+
+/// The maximum number of steps a test program may run for.
+const MAX_STEPS: u64 = 1_000_000;
+
+/// Asserts that source code following a standard prints the expected output
+/// for an input at every optimization level, both when interpreted and when
+/// compiled to machine code.
+fn assert_output(source: &str, standard: Standard, input: &str, expected: &str) {
+    for (name, level) in [
+        ("-O0", Level::None),
+        ("-O1", Level::Once),
+        ("-O2", Level::Full),
+    ] {
+        let builder = || {
+            Builder::from_playfield(Playfield::new(source, standard))
+                .options(Options {
+                    level,
+                    ..Options::default()
+                })
+                .limits(Limits {
+                    max_steps: Some(MAX_STEPS),
+                    ..Limits::default()
+                })
+        };
+
+        let mut io = BufferIo::new(input);
+        builder()
+            .compile()
+            .run_with(&mut io, &mut SeededRandom::new(0))
+            .expect("program should not exceed any limits");
+        assert_eq!(io.output(), expected, "interpreted at {name}");
+
+        #[cfg(feature = "jit")]
+        {
+            let mut io = BufferIo::new(input);
+            builder()
+                .compile()
+                .run_jit_with(&mut io, &mut SeededRandom::new(0))
+                .expect("program should not exceed any limits");
+            assert_eq!(io.output(), expected, "compiled at {name}");
+        }
+    }
+}
+
+/// Strict playfields are always 80x25 cells, and source code outside of them
+/// is truncated.
+#[test]
+fn truncates_strict_playfields() {
+    let source = format!("{}xy\n{}", "a".repeat(79), "b\n".repeat(30));
+    let playfield = Playfield::new(&source, Standard::Befunge93Strict);
+    assert_eq!(playfield.bounds(), (80, 25));
+    assert_eq!(playfield.get(79, 0).map(|v| v.into_char_lossy()), Some('x'));
+    assert_eq!(playfield.get(0, 24).map(|v| v.into_char_lossy()), Some('b'));
+    assert!(playfield.get(80, 0).is_none());
+
+    assert_eq!(Playfield::new("@", Standard::Fungus).bounds(), (1, 1));
+    assert_eq!(
+        Playfield::new("@", Standard::Befunge93Strict).bounds(),
+        (80, 25)
+    );
+}
+
+/// Gets past the 80th column read truncated cells in the default standard and
+/// wrap around to the first column in the strict standard.
+#[test]
+fn gets_truncated_cells() {
+    let source = format!("58*2*0g,@{}Z", " ".repeat(71));
+    assert_output(&source, Standard::Fungus, "", "Z");
+    assert_output(&source, Standard::Befunge93Strict, "", "5");
+
+    let source = format!("&&g,@{}Z", " ".repeat(75));
+    assert_output(&source, Standard::Fungus, "80\n0\n", "Z");
+    assert_output(&source, Standard::Befunge93Strict, "80\n0\n", "&");
+}
+
+/// Get and put coordinates wrap around strict playfields in both directions,
+/// and are out of bounds in the default standard.
+#[test]
+fn wraps_coordinates() {
+    let source = "\"x\"&&p&&g.@";
+    let input = "-1\n-1\n79\n24\n";
+    assert_output(source, Standard::Fungus, input, "0 ");
+    assert_output(source, Standard::Befunge93Strict, input, "120 ");
+
+    let input = "161\n52\n1\n2\n";
+    assert_output(source, Standard::Befunge93Strict, input, "120 ");
+
+    let source = "\"x\"01-01-p01-01-g.@";
+    assert_output(source, Standard::Fungus, "", "0 ");
+    assert_output(source, Standard::Befunge93Strict, "", "120 ");
+}
+
+/// Programs following different standards are cached separately.
+#[test]
+fn keys_programs_by_standard() {
+    let key = |standard| Key::new(&Playfield::new("12+.@", standard), &Options::default());
+    assert!(key(Standard::Fungus) != key(Standard::Befunge93Strict));
+}
+
+/// The command line follows the standard set with `--std`.
+#[test]
+fn sets_standard_from_command_line() {
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_fungus"))
+            .args(args)
+            .args(["-e", "\"x\"01-01-p01-01-g.@"])
+            .output()
+            .expect("fungus should run");
+
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).expect("output should be UTF-8")
+    };
+
+    assert_eq!(run(&[]), "0 ");
+    assert_eq!(run(&["--std", "fungus"]), "0 ");
+    assert_eq!(run(&["--std=93-strict"]), "120 ");
+}
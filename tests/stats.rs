@@ -0,0 +1,105 @@
+//! Tests for statistics about optimizing programs.
+
+use std::process::Command;
+
+use fungus::{
+    Builder,
+    optimize::{Level, Options, Stats, Step},
+};
+
+/// Compiles source code with an optimization level and returns its statistics.
+fn stats(source: &str, level: Level) -> Stats {
+    let options = Options {
+        level,
+        ..Options::default()
+    };
+
+    Builder::new(source)
+        .options(options)
+        .compile()
+        .stats()
+        .clone()
+}
+
+/// Returns the number of changes made by an optimization step.
+fn changes(stats: &Stats, step: Step) -> usize {
+    stats
+        .step_changes
+        .iter()
+        .find(|(s, _)| *s == step)
+        .map_or(0, |(_, c)| *c)
+}
+
+/// The size of the program is counted before and after optimization, along with
+/// the passes and the changes made by each step.
+#[test]
+fn counts_changes() {
+    let stats = stats("12+.@", Level::Full);
+    assert_eq!((stats.blocks_before, stats.blocks_after), (6, 1));
+    assert_eq!(
+        (stats.instructions_before, stats.instructions_after),
+        (4, 1)
+    );
+    assert_eq!(stats.passes, 2);
+
+    assert!(stats.step_changes.iter().map(|(s, _)| *s).eq(Step::ALL));
+    assert_eq!(changes(&stats, Step::MergeBlocks), 5);
+    assert_eq!(changes(&stats, Step::ReplaceInstructions), 2);
+    assert_eq!(changes(&stats, Step::ThreadJumps), 0);
+}
+
+/// Changes are counted for the steps that made them when steps are disabled.
+#[test]
+fn counts_changes_with_disabled_steps() {
+    let options = Options {
+        disabled_steps: vec![Step::MergeBlocks],
+        ..Options::default()
+    };
+
+    let stats = Builder::new("12+.@")
+        .options(options)
+        .compile()
+        .stats()
+        .clone();
+    assert_eq!(changes(&stats, Step::MergeBlocks), 0);
+    assert_eq!(changes(&stats, Step::RemoveUnreachableBlocks), 1);
+    assert_eq!(changes(&stats, Step::ReplaceJumpsToExits), 1);
+}
+
+/// Nothing is changed at optimization level 0.
+#[test]
+fn counts_no_changes_at_level_0() {
+    let stats = stats("12+.@", Level::None);
+    assert_eq!((stats.blocks_before, stats.blocks_after), (6, 6));
+    assert_eq!(
+        (stats.instructions_before, stats.instructions_after),
+        (4, 4)
+    );
+    assert_eq!(stats.passes, 0);
+    assert!(stats.step_changes.iter().all(|(_, c)| *c == 0));
+}
+
+/// Puts with constant coordinates that can't modify the program are lowered,
+/// and other puts stay dynamic.
+#[test]
+fn counts_puts() {
+    let stats = stats("\"a\"00p&&&p@", Level::Full);
+    assert_eq!(stats.dynamic_puts, 1);
+    assert_eq!(stats.lowered_puts, 1);
+}
+
+/// The command line flag prints the statistics to the standard error stream.
+#[test]
+fn prints_stats_from_command_line() {
+    let output = Command::new(env!("CARGO_BIN_EXE_fungus"))
+        .args(["--stats", "-e", "12+.@"])
+        .output()
+        .expect("fungus should run");
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"3 ");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("optimization statistics:\n"));
+    assert!(stderr.contains("    passes                        2\n"));
+    assert!(stderr.contains("        merge-blocks              5\n"));
+}
@@ -0,0 +1,67 @@
+//! Tests for synchronizing static variables with the playfield.
+
+use fungus::{
+    Builder, Playfield,
+    interpret::{BufferIo, OsRandom},
+};
+
+/// Runs source code with input and returns its output and final playfield.
+fn run(source: &str, input: &str) -> (String, Playfield) {
+    let mut io = BufferIo::new(input);
+    let playfield = Builder::new(source)
+        .compile()
+        .run_with(&mut io, &mut OsRandom)
+        .expect("program should not exceed any limits");
+
+    (io.into_output(), playfield)
+}
+
+/// Returns the value of a playfield cell as a character.
+fn cell(playfield: &Playfield, x: usize, y: usize) -> char {
+    playfield
+        .get(x, y)
+        .expect("position should be in bounds")
+        .into_char_lossy()
+}
+
+/// Dynamic gets read values written by static puts.
+#[test]
+fn stores_before_dynamic_gets() {
+    let (output, _) = run("\"a\"02p&&g,@\n\nx", "0\n2\n");
+    assert_eq!(output, "a");
+}
+
+/// Static gets read values written by dynamic puts.
+#[test]
+fn loads_after_dynamic_puts() {
+    let (output, _) = run("&&&p02g.@\n\nx", "66\n0\n2\n");
+    assert_eq!(output, "66 ");
+}
+
+/// The final playfield contains values written by static puts.
+#[test]
+fn stores_when_program_ends() {
+    let (output, playfield) = run("\"a\"02p\"b\"12p02g,@\n\nxy", "");
+    assert_eq!(output, "a");
+    assert_eq!(cell(&playfield, 0, 2), 'a');
+    assert_eq!(cell(&playfield, 1, 2), 'b');
+}
+
+/// The program is recompiled from a playfield containing values written by
+/// static puts.
+#[test]
+fn stores_before_recompiling() {
+    let source = [
+        "\"9\"77+2p\"v\"&&p @",
+        "",
+        "              x",
+        "              .",
+        "              @",
+    ]
+    .join("\n");
+
+    let (output, playfield) = run(&source, "14\n0\n");
+    assert_eq!(output, "9 ");
+    assert_eq!(cell(&playfield, 14, 0), 'v');
+    assert_eq!(cell(&playfield, 14, 2), '9');
+}